@@ -3,6 +3,7 @@ use crate::interval::Interval;
 use crate::ray::Ray;
 use crate::vec3::{axes3, Axis3, Point3, Vec3};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone)]
 pub struct AABB {
     intervals : [Interval; 3]
@@ -87,9 +88,9 @@ impl AABB {
     pub fn union(&self, other : &AABB) -> AABB {
         AABB {
             intervals : [
-                self.x().union(&other.x()),
-                self.y().union(&other.y()),
-                self.z().union(&other.z())]
+                self.x().union(other.x()),
+                self.y().union(other.y()),
+                self.z().union(other.z())]
 
         }
     }
@@ -120,6 +121,15 @@ impl AABB {
         self.intervals[2].pad(min);
     }
 
+    pub fn grow(&self, amount : f64) -> AABB {
+        AABB {
+            intervals : [
+                Interval::new(self.x().min - amount, self.x().max + amount),
+                Interval::new(self.y().min - amount, self.y().max + amount),
+                Interval::new(self.z().min - amount, self.z().max + amount)]
+        }
+    }
+
     pub fn min_point(&self) -> Point3 {
         Point3::new(
             self.x().min,
@@ -0,0 +1,147 @@
+use crate::quaternion::Quaternion;
+use crate::vec3::{Point3, Vec3};
+
+pub trait Interpolate : Copy {
+    fn interpolate(&self, other : &Self, s : f64) -> Self;
+}
+
+impl Interpolate for Vec3 {
+    fn interpolate(&self, other : &Vec3, s : f64) -> Vec3 {
+        (1.0 - s) * self + s * other
+    }
+}
+
+impl Interpolate for Quaternion {
+    fn interpolate(&self, other : &Quaternion, s : f64) -> Quaternion {
+        self.slerp(other, s)
+    }
+}
+
+// Values at given times, held constant before the first and after the last key
+pub struct Keyframes<T : Interpolate> {
+    keys : Vec<(f64, T)>
+}
+
+impl<T : Interpolate> Keyframes<T> {
+    pub fn new() -> Keyframes<T> {
+        Keyframes { keys : Vec::new() }
+    }
+
+    pub fn add(&mut self, time : f64, value : &T) {
+        let index = self.keys.partition_point(|(key_time, _)| *key_time <= time);
+        self.keys.insert(index, (time, *value));
+    }
+
+    pub fn sample(&self, time : f64) -> Option<T> {
+        let index = self.keys.partition_point(|(key_time, _)| *key_time <= time);
+        if index == 0 {
+            return self.keys.first().map(|(_, value)| *value);
+        }
+        if index == self.keys.len() {
+            return self.keys.last().map(|(_, value)| *value);
+        }
+        let (t0, v0) = &self.keys[index - 1];
+        let (t1, v1) = &self.keys[index];
+        let s = (time - t0) / (t1 - t0);
+        Some(v0.interpolate(v1, s))
+    }
+
+    pub fn times(&self) -> impl Iterator<Item = f64> + '_ {
+        self.keys.iter().map(|(time, _)| *time)
+    }
+}
+
+// Scale then rotate then translate
+#[derive(Copy, Clone)]
+pub struct Transform {
+    translation : Vec3,
+    rotation : Quaternion,
+    scale : Vec3
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            translation : Vec3::zero(),
+            rotation : Quaternion::identity(),
+            scale : Vec3::new(1.0, 1.0, 1.0)
+        }
+    }
+
+    pub fn apply_point(&self, point : &Point3) -> Point3 {
+        self.apply_vector(point) + self.translation
+    }
+
+    pub fn apply_vector(&self, vec : &Vec3) -> Vec3 {
+        self.rotation.rotate(&scale_vec(&self.scale, vec))
+    }
+
+    // Inverse transpose so normals stay perpendicular under non-uniform scale
+    pub fn apply_normal(&self, normal : &Vec3) -> Vec3 {
+        self.rotation.rotate(&inv_scale_vec(&self.scale, normal))
+    }
+
+    pub fn inverse_point(&self, point : &Point3) -> Point3 {
+        self.inverse_vector(&(point - self.translation))
+    }
+
+    pub fn inverse_vector(&self, vec : &Vec3) -> Vec3 {
+        inv_scale_vec(&self.scale, &self.rotation.conjugate().rotate(vec))
+    }
+}
+
+fn scale_vec(scale : &Vec3, vec : &Vec3) -> Vec3 {
+    Vec3::new(scale.x() * vec.x(), scale.y() * vec.y(), scale.z() * vec.z())
+}
+
+fn inv_scale_vec(scale : &Vec3, vec : &Vec3) -> Vec3 {
+    Vec3::new(vec.x() / scale.x(), vec.y() / scale.y(), vec.z() / scale.z())
+}
+
+// Independent keyframe tracks for each part of the transform
+pub struct Animation {
+    translation : Keyframes<Vec3>,
+    rotation : Keyframes<Quaternion>,
+    scale : Keyframes<Vec3>
+}
+
+impl Animation {
+    pub fn new() -> Animation {
+        Animation {
+            translation : Keyframes::new(),
+            rotation : Keyframes::new(),
+            scale : Keyframes::new()
+        }
+    }
+
+    pub fn add_translation(&mut self, time : f64, translation : &Vec3) {
+        self.translation.add(time, translation);
+    }
+
+    pub fn add_rotation(&mut self, time : f64, rotation : &Quaternion) {
+        self.rotation.add(time, &rotation.unit());
+    }
+
+    pub fn add_scale(&mut self, time : f64, scale : &Vec3) {
+        self.scale.add(time, scale);
+    }
+
+    pub fn at(&self, time : f64) -> Transform {
+        let identity = Transform::identity();
+        Transform {
+            translation : self.translation.sample(time).unwrap_or(identity.translation),
+            rotation : self.rotation.sample(time).unwrap_or(identity.rotation),
+            scale : self.scale.sample(time).unwrap_or(identity.scale)
+        }
+    }
+
+    pub fn key_times(&self) -> Vec<f64> {
+        let mut times : Vec<f64> = self.translation.times()
+            .chain(self.rotation.times())
+            .chain(self.scale.times())
+            .collect();
+        times.sort_by(f64::total_cmp);
+        times.dedup();
+        times
+    }
+}
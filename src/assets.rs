@@ -0,0 +1,82 @@
+use std::env::{split_paths, var_os};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use image::{DynamicImage, ImageError};
+
+// Environment variable listing extra asset directories, separated like PATH
+pub const ASSETS_VARIABLE : &str = "RUST_RAY_ASSETS";
+
+#[derive(Debug)]
+pub enum AssetError {
+    // None of the candidate paths held a file
+    NotFound { name : String, tried : Vec<PathBuf> },
+    // The file was found but could not be read as an image
+    Decode { path : PathBuf, error : ImageError }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetError::NotFound { name, tried } => {
+                write!(f, "could not find {}, tried", name)?;
+                for path in tried {
+                    write!(f, " {}", path.display())?;
+                }
+                Ok(())
+            },
+            AssetError::Decode { path, error } => write!(f, "could not decode {}: {}", path.display(), error)
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+// Finds assets by name in a list of directories searched in order, absolute names are used as given
+pub struct AssetResolver {
+    search_paths : Vec<PathBuf>
+}
+
+impl AssetResolver {
+    pub fn new() -> AssetResolver {
+        AssetResolver { search_paths : Vec::new() }
+    }
+
+    // Directories from the command line, then the environment variable, then the data directory
+    // under the working directory and the one shipped with the source
+    pub fn standard(command_line : &[PathBuf]) -> AssetResolver {
+        let mut resolver = AssetResolver::new();
+        for path in command_line {
+            resolver.add_search_path(path);
+        }
+        if let Some(paths) = var_os(ASSETS_VARIABLE) {
+            for path in split_paths(&paths) {
+                resolver.add_search_path(&path);
+            }
+        }
+        resolver.add_search_path(Path::new("./data"));
+        resolver.add_search_path(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/data")));
+        resolver
+    }
+
+    pub fn add_search_path(&mut self, path : &Path) {
+        self.search_paths.push(path.to_path_buf());
+    }
+
+    pub fn resolve(&self, name : &str) -> Result<PathBuf, AssetError> {
+        let path = Path::new(name);
+        let candidates = if path.is_absolute() {
+            vec![path.to_path_buf()]
+        } else {
+            self.search_paths.iter().map(|directory| directory.join(path)).collect()
+        };
+        match candidates.iter().find(|candidate| candidate.is_file()) {
+            Some(found) => Ok(found.clone()),
+            None => Err(AssetError::NotFound { name : name.to_string(), tried : candidates })
+        }
+    }
+
+    pub fn load_image(&self, name : &str) -> Result<DynamicImage, AssetError> {
+        let path = self.resolve(name)?;
+        image::open(&path).map_err(|error| AssetError::Decode { path, error })
+    }
+}
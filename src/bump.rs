@@ -0,0 +1,119 @@
+use rand::rngs::ThreadRng;
+use crate::colour::Colour;
+use crate::hittables::HitRecord;
+use crate::materials::Material;
+use crate::ray::Ray;
+use crate::textures::{Scalar, Texture};
+use crate::vec3::{cross, dot, UnitVec3, Vec3};
+
+// Step in texture coordinates for differencing a height texture
+const HEIGHT_STEP : f64 = 1e-4;
+
+enum Detail<'tex> {
+    // Tangent space normal encoded in [0, 1] with z away from the surface
+    NormalMap(&'tex dyn Texture),
+    // Displacement along the normal given by the average of the channels times the scale
+    Height(&'tex dyn Texture, f64)
+}
+
+// Shades the wrapped material with a normal perturbed by a normal map or a height texture
+pub struct BumpMapped<'mat> {
+    material : &'mat dyn Material,
+    detail : Detail<'mat>
+}
+
+impl<'mat> BumpMapped<'mat> {
+    pub fn normal_map(material : &'mat dyn Material, normals : &'mat dyn Texture) -> BumpMapped<'mat> {
+        BumpMapped {
+            material,
+            detail : Detail::NormalMap(normals)
+        }
+    }
+
+    pub fn height(material : &'mat dyn Material, height : &'mat dyn Texture, scale : f64) -> BumpMapped<'mat> {
+        BumpMapped {
+            material,
+            detail : Detail::Height(height, scale)
+        }
+    }
+
+    // Perturbed normal facing the same way as the hit normal, None without tangents
+    fn perturbed(&self, hit_record : &HitRecord) -> Option<UnitVec3> {
+        let outward = cross(&hit_record.dpdu, &hit_record.dpdv);
+        if outward.length_squared() <= 0.0 {
+            return None;
+        }
+        let outward = outward.unit();
+        let normal = match self.detail {
+            Detail::NormalMap(normals) => {
+                let local = 2.0 * hit_record.texture_value(normals) - Colour::new(1.0, 1.0, 1.0);
+                let tangent = hit_record.dpdu.unit();
+                let bitangent = cross(&outward, &tangent);
+                local.x() * tangent + local.y() * bitangent + local.z() * outward
+            },
+            Detail::Height(height, scale) => {
+                let displacement = |hit : &HitRecord| scale * Scalar::Texture(height).value_at_hit(hit);
+                let base = displacement(hit_record);
+                // Neighbouring hits a small step along each tangent, in object space too for projections
+                let along_u = displacement(&HitRecord {
+                    u : hit_record.u + HEIGHT_STEP,
+                    point : hit_record.point + HEIGHT_STEP * hit_record.dpdu,
+                    object_point : hit_record.object_point + HEIGHT_STEP * hit_record.object_dpdu,
+                    ..*hit_record
+                });
+                let along_v = displacement(&HitRecord {
+                    v : hit_record.v + HEIGHT_STEP,
+                    point : hit_record.point + HEIGHT_STEP * hit_record.dpdv,
+                    object_point : hit_record.object_point + HEIGHT_STEP * hit_record.object_dpdv,
+                    ..*hit_record
+                });
+                let dpdu = hit_record.dpdu + (along_u - base) / HEIGHT_STEP * outward;
+                let dpdv = hit_record.dpdv + (along_v - base) / HEIGHT_STEP * outward;
+                cross(&dpdu, &dpdv)
+            }
+        };
+        if normal.length_squared() <= 0.0 {
+            return None;
+        }
+        let normal = normal.unit();
+        if dot(&outward, &hit_record.normal) < 0.0 {
+            Some(-normal)
+        } else {
+            Some(normal)
+        }
+    }
+
+    // Hit with the shading normal, kept geometric when the perturbed one faces away from the ray
+    fn shading<'a>(&self, ray_in : &Ray, hit_record : &HitRecord<'a>) -> HitRecord<'a> {
+        match self.perturbed(hit_record) {
+            Some(normal) if dot(&ray_in.direction, &normal) < 0.0 => HitRecord { normal, ..*hit_record },
+            _ => HitRecord { ..*hit_record }
+        }
+    }
+}
+
+impl<'mat> Material for BumpMapped<'mat> {
+    fn scatter(&self, rng : &mut ThreadRng, ray_in : &Ray, hit_record : &HitRecord) -> Option<(Colour, Ray)> {
+        self.material.scatter(rng, ray_in, &self.shading(ray_in, hit_record))
+    }
+
+    fn emitted(&self, ray_in : &Ray, hit_record : &HitRecord) -> Colour {
+        self.material.emitted(ray_in, hit_record)
+    }
+
+    fn evaluate(&self, ray_in : &Ray, hit_record : &HitRecord, direction : &Vec3) -> Option<(Colour, f64)> {
+        self.material.evaluate(ray_in, &self.shading(ray_in, hit_record), direction)
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.material.is_dispersive()
+    }
+
+    fn subsurface<'a>(&'a self, rng : &mut ThreadRng, ray_in : &Ray, hit_record : &HitRecord) -> Option<(Colour, HitRecord<'a>)> {
+        self.material.subsurface(rng, ray_in, &self.shading(ray_in, hit_record))
+    }
+
+    fn cutout(&self, rng : &mut ThreadRng, hit_record : &HitRecord) -> bool {
+        self.material.cutout(rng, hit_record)
+    }
+}
@@ -4,14 +4,17 @@ use rand::{thread_rng, Rng};
 use rand::rngs::ThreadRng;
 use image::{RgbImage};
 use rayon::iter::{IntoParallelRefMutIterator, IndexedParallelIterator, ParallelIterator};
-use crate::colour::{attenuate, write_colour, Colour};
-use crate::hittables::{Hittable, BVH};
+use crate::colour::{attenuate, write_colour, Colour, ColourSpace};
+use crate::hittables::{HitRecord, Hittable, BVH};
+use crate::environment::{Environment, SolidBackground};
+use crate::lights::Light;
 use crate::interval::Interval;
-use crate::ray::Ray;
+use crate::ray::{Ray, RayDifferentials};
+use crate::spectrum::SampledWavelengths;
 use crate::vec3::{cross, random_in_disc, Point3, Vec3};
 
 
-pub struct Camera {
+pub struct Camera<'a> {
     image_width : u32,
     image_height : u32,
     samples_per_pixel : u32,
@@ -22,11 +25,16 @@ pub struct Camera {
     defocus_u : Vec3,
     defocus_v : Vec3,
     max_depth : u8,
-    background : Colour
+    environment : Box<dyn Environment + 'a>,
+    shutter : Interval,
+    lights : Vec<Box<dyn Light + 'a>>,
+    spectral : bool,
+    output_space : ColourSpace
 }
 
-impl Camera {
+impl<'a> Camera<'a> {
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         look_from : &Point3,
         look_at : &Point3,
@@ -39,7 +47,7 @@ impl Camera {
         defocus_distance : f64,
         defocus_angle : f64,
         background : Colour
-    ) -> Camera {
+    ) -> Camera<'a> {
         let image_height = max(
             1,
             ((image_width as f64) / aspect_ratio) as u32
@@ -77,10 +85,43 @@ impl Camera {
             defocus_u,
             defocus_v,
             max_depth,
-            background
+            environment : Box::new(SolidBackground::new(&background)),
+            shutter : Interval::new(0.0, 1.0),
+            lights : Vec::new(),
+            spectral : false,
+            output_space : ColourSpace::Srgb
         }
     }
 
+    // Shutter angle in degrees, 360 keeps the shutter open for the whole frame
+    pub fn set_frame(&mut self, frame : u32, frames_per_second : f64, shutter_angle : f64) {
+        let open = frame as f64 / frames_per_second;
+        let duration = (shutter_angle / 360.0) / frames_per_second;
+        self.shutter = Interval::new(open, open + duration);
+    }
+
+    pub fn shutter(&self) -> Interval {
+        self.shutter
+    }
+
+    pub fn set_environment(&mut self, environment : Box<dyn Environment + 'a>) {
+        self.environment = environment;
+    }
+
+    pub fn add_light(&mut self, light : Box<dyn Light + 'a>) {
+        self.lights.push(light);
+    }
+
+    // Trace paths at sampled wavelengths rather than in rgb, needed for dispersion
+    pub fn set_spectral(&mut self, spectral : bool) {
+        self.spectral = spectral;
+    }
+
+    // Encoding of the written image, the render itself is linear light
+    pub fn set_output_space(&mut self, output_space : ColourSpace) {
+        self.output_space = output_space;
+    }
+
     pub fn render(&self, image_file : &str, world : &BVH)
     {
         let mut image = RgbImage::new(self.image_width, self.image_height);
@@ -89,7 +130,7 @@ impl Camera {
             print!("\rScanlines remaining: {}       \n", self.image_height - j);
             let row = self.row(j, world);
             for (i, colour) in row.iter().enumerate() {
-                write_colour(&mut image, i as u32, j, colour);
+                write_colour(&mut image, i as u32, j, colour, &self.output_space);
             }
         }
         image.save(image_file).expect("Unable to write image");
@@ -108,8 +149,15 @@ impl Camera {
         let mut rng = thread_rng();
         let mut pixel_colour = Colour::zero();
         for _ in 0..self.samples_per_pixel {
-            let ray = self.ray(&mut rng, i, j);
-            pixel_colour += self.ray_colour(&mut rng, world, &ray, self.max_depth);
+            let mut ray = self.ray(&mut rng, i, j);
+            if self.spectral {
+                let wavelengths = SampledWavelengths::sample(&mut rng);
+                ray.wavelength = Some(wavelengths.hero());
+                let values = self.ray_colour(&mut rng, world, &ray, self.max_depth, None, Some(&wavelengths));
+                pixel_colour += wavelengths.resolve(&values);
+            } else {
+                pixel_colour += self.ray_colour(&mut rng, world, &ray, self.max_depth, None, None);
+            }
         }
         pixel_colour /= self.samples_per_pixel as f64;
         pixel_colour
@@ -125,8 +173,17 @@ impl Camera {
             + (u * self.pixel_delta_u)
             + (v * self.pixel_delta_v);
         let origin = self.defocus_disc_sample(rng);
-        let time = rng.gen::<f64>();
-        Ray::between(&origin, &viewpoint_pt, time)
+        let time = self.shutter.random(rng);
+        let mut ray = Ray::between(&origin, &viewpoint_pt, time);
+        // Samples share a pixel so each covers a fraction of it
+        let spacing = (1.0 / (self.samples_per_pixel as f64).sqrt()).max(0.125);
+        ray.differentials = Some(RayDifferentials {
+            x_origin : origin,
+            x_direction : viewpoint_pt + spacing * self.pixel_delta_u - origin,
+            y_origin : origin,
+            y_direction : viewpoint_pt + spacing * self.pixel_delta_v - origin
+        });
+        ray
     }
 
     fn defocus_disc_sample<R>(&self, rng : &mut R) -> Point3
@@ -143,23 +200,124 @@ impl Camera {
         Vec3::new(x, y, 0.0)
     }
 
-    fn ray_colour<Hit>(&self, rng : &mut ThreadRng, world : &Hit, ray : &Ray, max_depth : u8) -> Colour
+    // Scatter pdf is the pdf the previous bounce chose this ray with if lights could be sampled there.
+    // With wavelengths the colour returned holds the radiance at each sampled wavelength
+    fn ray_colour<Hit>(
+        &self,
+        rng : &mut ThreadRng,
+        world : &Hit,
+        ray : &Ray,
+        max_depth : u8,
+        scatter_pdf : Option<f64>,
+        wavelengths : Option<&SampledWavelengths>
+    ) -> Colour
     where Hit : Hittable
     {
         if max_depth == 0 {
             return Colour::new(0.0, 0.0, 0.0);
         }
         let initial_t = Interval { min: 0.001, max: f64::MAX };
-        if let Some(hit) = world.hit(ray, &initial_t, rng) {
-            let emission = hit.material.emitted(hit.u, hit.v, &hit.point);
-            if let Some((attenuation, scattered_ray)) = hit.material.scatter(rng, ray, &hit) {
-                let scattered = self.ray_colour(rng, world, &scattered_ray, max_depth - 1);
-                emission + attenuate(&attenuation, &scattered)
+        if let Some(mut hit) = world.hit(ray, &initial_t, rng) {
+            hit.set_footprint(ray);
+            let emission = to_path(wavelengths, &hit.material.emitted(ray, &hit));
+            // Light travelling under the surface is shaded where it leaves
+            let (throughput, hit) = match hit.material.subsurface(rng, ray, &hit) {
+                Some((throughput, exit)) => (to_path(wavelengths, &throughput), exit),
+                None => (Colour::new(1.0, 1.0, 1.0), hit)
+            };
+            if throughput.length_squared() <= 0.0 {
+                return emission;
+            }
+            let direct = self.direct_light(rng, world, ray, &hit, wavelengths);
+            if let Some((attenuation, mut scattered_ray)) = hit.material.scatter(rng, ray, &hit) {
+                let pdf = hit.material.evaluate(ray, &hit, &scattered_ray.direction).map(|(_, pdf)| pdf);
+                scattered_ray.wavelength = ray.wavelength;
+                let mut attenuation = to_path(wavelengths, &attenuation);
+                let mut path_wavelengths = wavelengths.copied();
+                if let Some(sampled) = wavelengths {
+                    if hit.material.is_dispersive() {
+                        let (terminated, weight) = sampled.terminate_secondary();
+                        attenuation = attenuate(&attenuation, &weight);
+                        path_wavelengths = Some(terminated);
+                    }
+                }
+                let scattered = self.ray_colour(
+                    rng, world, &scattered_ray, max_depth - 1, pdf, path_wavelengths.as_ref()
+                );
+                emission + attenuate(&throughput, &(direct + attenuate(&attenuation, &scattered)))
             } else {
-                emission
+                emission + attenuate(&throughput, &direct)
             }
         } else {
-            self.background
+            let background = to_path(wavelengths, &self.environment.value(&ray.direction));
+            if let Some(pdf) = scatter_pdf {
+                // Environment was also sampled directly from the last hit
+                background * power_heuristic(pdf, self.environment.pdf(&ray.direction))
+            } else {
+                background
+            }
         }
     }
-}
\ No newline at end of file
+
+    // Sample each light directly as rays can never hit them
+    fn direct_light<Hit>(
+        &self,
+        rng : &mut ThreadRng,
+        world : &Hit,
+        ray : &Ray,
+        hit : &HitRecord,
+        wavelengths : Option<&SampledWavelengths>
+    ) -> Colour
+    where Hit : Hittable
+    {
+        let mut total = Colour::zero();
+        for light in self.lights.iter() {
+            let Some(sample) = light.sample(rng, &hit.point) else {
+                continue;
+            };
+            let Some((bsdf, _)) = hit.material.evaluate(ray, hit, &sample.direction) else {
+                // Only discrete directions so light sampling cannot help
+                return Colour::zero();
+            };
+            if bsdf.length_squared() <= 0.0 {
+                continue;
+            }
+            let shadow_ray = Ray::new(&hit.point, &sample.direction, ray.time);
+            let shadow_t = Interval::new(0.001, sample.distance - 0.001);
+            if world.hit(&shadow_ray, &shadow_t, rng).is_none() {
+                let light = attenuate(&to_path(wavelengths, &bsdf), &to_path(wavelengths, &sample.radiance));
+                total += light / sample.pdf;
+            }
+        }
+        if let Some(sample) = self.environment.sample(rng) {
+            if let Some((bsdf, pdf)) = hit.material.evaluate(ray, hit, &sample.direction) {
+                let shadow_ray = Ray::new(&hit.point, &sample.direction, ray.time);
+                let shadow_t = Interval::new(0.001, f64::MAX);
+                if bsdf.length_squared() > 0.0 && world.hit(&shadow_ray, &shadow_t, rng).is_none() {
+                    let weight = power_heuristic(sample.pdf, pdf);
+                    let light = attenuate(&to_path(wavelengths, &bsdf), &to_path(wavelengths, &sample.radiance));
+                    total += weight * light / sample.pdf;
+                }
+            }
+        }
+        total
+    }
+}
+
+// Rgb colours are turned into values at the sampled wavelengths on spectral paths
+fn to_path(wavelengths : Option<&SampledWavelengths>, colour : &Colour) -> Colour {
+    match wavelengths {
+        Some(sampled) => sampled.upsample(colour),
+        None => *colour
+    }
+}
+
+fn power_heuristic(pdf : f64, other_pdf : f64) -> f64 {
+    let a = pdf * pdf;
+    let b = other_pdf * other_pdf;
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        1.0
+    }
+}
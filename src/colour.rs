@@ -13,37 +13,72 @@ pub fn attenuate(attenuation : &Colour, colour : &Colour) -> Colour {
     )
 }
 
-fn gamma_correct(linear : f64) -> f64 {
-    if linear > 0.0 {
-        linear.sqrt()
-    } else {
-        linear
-    }
+// CIE XYZ to linear sRGB primaries with a D65 white point
+pub fn xyz_to_rgb(x : f64, y : f64, z : f64) -> Colour {
+    Colour::new(
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z
+    )
 }
 
-pub fn read_colour(
-    image : &RgbImage,
-    i : u32,
-    j : u32
-) -> Colour {
-    let b_colour = image.get_pixel(i, j);
-    let r = b_colour.0[0] as f64 / 255.0;
-    let g = b_colour.0[1] as f64 / 255.0;
-    let b = b_colour.0[2] as f64 / 255.0;
-    Colour::new(r, g, b)
+// How values in an image relate to linear light
+#[derive(Copy, Clone)]
+pub enum ColourSpace {
+    // Piecewise sRGB curve of most 8-bit images and displays
+    Srgb,
+    // Plain power curve, 2.0 was the original output of the renderer
+    Gamma(f64),
+    // Already linear light like HDR images
+    Linear,
+    // Data that is not a colour such as normal maps and masks, used as stored
+    Raw
+}
+
+impl ColourSpace {
+    pub fn decode(&self, encoded : f64) -> f64 {
+        match self {
+            ColourSpace::Srgb => {
+                if encoded <= 0.04045 {
+                    encoded / 12.92
+                } else {
+                    ((encoded + 0.055) / 1.055).powf(2.4)
+                }
+            },
+            ColourSpace::Gamma(gamma) => encoded.max(0.0).powf(*gamma),
+            ColourSpace::Linear | ColourSpace::Raw => encoded
+        }
+    }
 
+    pub fn encode(&self, linear : f64) -> f64 {
+        if linear <= 0.0 {
+            return linear;
+        }
+        match self {
+            ColourSpace::Srgb => {
+                if linear <= 0.0031308 {
+                    12.92 * linear
+                } else {
+                    1.055 * linear.powf(1.0 / 2.4) - 0.055
+                }
+            },
+            ColourSpace::Gamma(gamma) => linear.powf(1.0 / gamma),
+            ColourSpace::Linear | ColourSpace::Raw => linear
+        }
+    }
 }
 
 pub fn write_colour(
     image : &mut RgbImage,
     i : u32,
     j : u32,
-    colour : &Colour
+    colour : &Colour,
+    space : &ColourSpace
 ) {
     let range = Interval {min : 0.0, max : 1.0};
-    let r = gamma_correct(colour.x());
-    let g = gamma_correct(colour.y());
-    let b = gamma_correct(colour.z());
+    let r = space.encode(colour.x());
+    let g = space.encode(colour.y());
+    let b = space.encode(colour.z());
     let b_red = (range.clamp(r) * 256.0) as u8;
     let b_green = (range.clamp(g) * 256.0) as u8;
     let b_blue = (range.clamp(b) * 256.0) as u8;
@@ -0,0 +1,192 @@
+use std::f64::consts::PI;
+use rand::Rng;
+use rand::rngs::ThreadRng;
+use crate::assets::{AssetError, AssetResolver};
+use crate::colour::Colour;
+use crate::lights::LightSample;
+use crate::vec3::{UnitVec3, Vec3};
+
+// Light arriving from infinitely far away along rays that miss everything
+pub trait Environment : Sync {
+    fn value(&self, direction : &Vec3) -> Colour;
+
+    // Direction towards the environment weighted to bright areas, None if it is not worth sampling
+    fn sample(&self, _rng : &mut ThreadRng) -> Option<LightSample> {
+        None
+    }
+
+    fn pdf(&self, _direction : &Vec3) -> f64 {
+        0.0
+    }
+}
+
+pub struct SolidBackground {
+    colour : Colour
+}
+
+impl SolidBackground {
+    pub fn new(colour : &Colour) -> SolidBackground {
+        SolidBackground { colour : *colour }
+    }
+}
+
+impl Environment for SolidBackground {
+    fn value(&self, _direction : &Vec3) -> Colour {
+        self.colour
+    }
+}
+
+fn luminance(colour : &Colour) -> f64 {
+    0.2126 * colour.x() + 0.7152 * colour.y() + 0.0722 * colour.z()
+}
+
+// Piecewise constant distribution over [0, 1)
+struct Distribution1D {
+    func : Vec<f64>,
+    cdf : Vec<f64>,
+    integral : f64
+}
+
+impl Distribution1D {
+    fn new(func : Vec<f64>) -> Distribution1D {
+        let n = func.len() as f64;
+        let mut cdf = vec![0.0; func.len() + 1];
+        for i in 0..func.len() {
+            cdf[i + 1] = cdf[i] + func[i] / n;
+        }
+        let integral = cdf[func.len()];
+        if integral > 0.0 {
+            cdf.iter_mut().for_each(|val| *val /= integral);
+        } else {
+            cdf.iter_mut().enumerate().for_each(|(i, val)| *val = i as f64 / n);
+        }
+        Distribution1D { func, cdf, integral }
+    }
+
+    // Continuous sample in [0, 1) with its bucket and density
+    fn sample(&self, r : f64) -> (f64, usize, f64) {
+        let index = (self.cdf.partition_point(|val| *val <= r) - 1).min(self.func.len() - 1);
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 { (r - self.cdf[index]) / width } else { 0.0 };
+        let x = (index as f64 + offset) / self.func.len() as f64;
+        (x, index, self.density(index))
+    }
+
+    fn density(&self, index : usize) -> f64 {
+        if self.integral > 0.0 {
+            self.func[index] / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+// Lat-long image, u around the y axis and v from bottom to top like Sphere
+pub struct EnvironmentMap {
+    width : usize,
+    height : usize,
+    pixels : Vec<Colour>,
+    rotation_cos : f64,
+    rotation_sin : f64,
+    intensity : f64,
+    rows : Vec<Distribution1D>,
+    marginal : Distribution1D
+}
+
+impl EnvironmentMap {
+    // Rotation in degrees about the y axis
+    pub fn load(assets : &AssetResolver, name : &str, rotation : f64, intensity : f64) -> Result<EnvironmentMap, AssetError> {
+        let image = assets.load_image(name)?.into_rgb32f();
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+        let pixels : Vec<Colour> = image.pixels().map(
+            |pixel| Colour::new(pixel.0[0] as f64, pixel.0[1] as f64, pixel.0[2] as f64)
+        ).collect();
+        // Weight by solid angle so the stretched poles are not oversampled
+        let rows : Vec<Distribution1D> = (0..height).map(|j| {
+            let theta = PI * (j as f64 + 0.5) / height as f64;
+            let row = &pixels[j * width..(j + 1) * width];
+            Distribution1D::new(row.iter().map(|pixel| luminance(pixel) * theta.sin()).collect())
+        }).collect();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral).collect());
+        let angle = rotation * PI / 180.0;
+        Ok(EnvironmentMap {
+            width,
+            height,
+            pixels,
+            rotation_cos : angle.cos(),
+            rotation_sin : angle.sin(),
+            intensity,
+            rows,
+            marginal
+        })
+    }
+
+    // Rotate about y so the map turns by the rotation angle
+    fn map_direction(&self, direction : &Vec3) -> Vec3 {
+        Vec3::new(
+            self.rotation_cos * direction.x() + self.rotation_sin * direction.z(),
+            direction.y(),
+            -self.rotation_sin * direction.x() + self.rotation_cos * direction.z()
+        )
+    }
+
+    fn world_direction(&self, direction : &Vec3) -> Vec3 {
+        Vec3::new(
+            self.rotation_cos * direction.x() - self.rotation_sin * direction.z(),
+            direction.y(),
+            self.rotation_sin * direction.x() + self.rotation_cos * direction.z()
+        )
+    }
+
+    // Column and row from the top of the image
+    fn pixel_index(&self, direction : &Vec3) -> (usize, usize) {
+        let unit = self.map_direction(direction).unit();
+        let theta = unit.y().clamp(-1.0, 1.0).acos();
+        let phi = f64::atan2(-unit.z(), unit.x());
+        let u = phi / (2.0 * PI) + 0.5;
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((theta / PI * self.height as f64) as usize).min(self.height - 1);
+        (i, j)
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn value(&self, direction : &Vec3) -> Colour {
+        let (i, j) = self.pixel_index(direction);
+        self.intensity * self.pixels[j * self.width + i]
+    }
+
+    fn sample(&self, rng : &mut ThreadRng) -> Option<LightSample> {
+        if self.marginal.integral <= 0.0 {
+            return None;
+        }
+        let (v, j, pdf_v) = self.marginal.sample(rng.gen::<f64>());
+        let (u, i, pdf_u) = self.rows[j].sample(rng.gen::<f64>());
+        let theta = v * PI;
+        let phi = (u - 0.5) * 2.0 * PI;
+        let sin_th = theta.sin();
+        if sin_th <= 0.0 {
+            return None;
+        }
+        let local : UnitVec3 = Vec3::new(sin_th * phi.cos(), theta.cos(), -sin_th * phi.sin());
+        Some(LightSample {
+            direction : self.world_direction(&local),
+            distance : f64::MAX,
+            radiance : self.intensity * self.pixels[j * self.width + i],
+            pdf : pdf_u * pdf_v / (2.0 * PI * PI * sin_th)
+        })
+    }
+
+    fn pdf(&self, direction : &Vec3) -> f64 {
+        if self.marginal.integral <= 0.0 {
+            return 0.0;
+        }
+        let (i, j) = self.pixel_index(direction);
+        let sin_th = (1.0 - direction.unit().y().powi(2)).max(0.0).sqrt();
+        if sin_th <= 0.0 {
+            return 0.0;
+        }
+        self.rows[j].density(i) * self.marginal.density(j) / (2.0 * PI * PI * sin_th)
+    }
+}
@@ -1,14 +1,16 @@
 use std::f64::consts::PI;
 use crate::aabb::AABB;
+use crate::colour::Colour;
+use crate::animation::Animation;
 use crate::interval::Interval;
-use crate::materials::{Isotropic, Material};
+use crate::materials::{Isotropic, Material, Subsurface};
 use crate::ray::Ray;
 use crate::vec3::{cross, dot, Axis3, Point3, UnitVec3, Vec3};
 
 use ordered_float::NotNan;
 use rand::Rng;
 use rand::rngs::ThreadRng;
-use crate::textures::Texture;
+use crate::textures::{Footprint, Texture};
 
 pub struct HitRecord<'mat> {
     pub point : Point3,
@@ -17,15 +19,31 @@ pub struct HitRecord<'mat> {
     pub u : f64,
     pub v : f64,
     pub front_face : bool,
+    // Time of the ray that made the hit, for textures that change over the shutter
+    pub time : f64,
+    // Point and outward normal of the primitive before any transforms move it into the world
+    pub object_point : Point3,
+    pub object_normal : UnitVec3,
+    // Surface derivatives along the texture coordinates, zero if the surface has none
+    pub dpdu : Vec3,
+    pub dpdv : Vec3,
+    // The same derivatives before any transforms, alongside the object point
+    pub object_dpdu : Vec3,
+    pub object_dpdv : Vec3,
+    // Change in texture coordinates across a pixel when the ray has differentials
+    pub footprint : Option<Footprint>,
+    // Uniform in [0, 1) and drawn once per hit so materials picking a layer pick the same one every time
+    pub layer_sample : f64,
     pub material : &'mat dyn Material
 }
 pub trait Hittable: Sync {
-    fn hit(&self, ray : &Ray, ray_t : &Interval, rng : &mut ThreadRng) -> Option<HitRecord>;
+    fn hit(&self, ray : &Ray, ray_t : &Interval, rng : &mut ThreadRng) -> Option<HitRecord<'_>>;
     fn bounding_box(&self) -> AABB;
 }
 
 impl<'mat> HitRecord<'mat> {
     fn new(point : &Point3, t : f64, ray : &Ray, outward_normal : &Vec3, u : f64, v : f64, material : &'mat dyn Material) -> HitRecord<'mat> {
+        let layer_sample = rand::random::<f64>();
         if dot(&ray.direction, outward_normal) < 0.0 {
             HitRecord {
                 point : *point,
@@ -34,6 +52,15 @@ impl<'mat> HitRecord<'mat> {
                 u,
                 v,
                 front_face : true,
+                time : ray.time,
+                object_point : *point,
+                object_normal : *outward_normal,
+                dpdu : Vec3::zero(),
+                dpdv : Vec3::zero(),
+                object_dpdu : Vec3::zero(),
+                object_dpdv : Vec3::zero(),
+                footprint : None,
+                layer_sample,
                 material
             }
         } else {
@@ -44,10 +71,62 @@ impl<'mat> HitRecord<'mat> {
                 u,
                 v,
                 front_face : false,
+                time : ray.time,
+                object_point : *point,
+                object_normal : *outward_normal,
+                dpdu : Vec3::zero(),
+                dpdv : Vec3::zero(),
+                object_dpdu : Vec3::zero(),
+                object_dpdv : Vec3::zero(),
+                footprint : None,
+                layer_sample,
                 material
             }
         }
     }
+
+    fn set_tangents(&mut self, dpdu : &Vec3, dpdv : &Vec3) {
+        self.dpdu = *dpdu;
+        self.dpdv = *dpdv;
+        self.object_dpdu = *dpdu;
+        self.object_dpdv = *dpdv;
+    }
+
+    // Project the ray's differentials onto the tangent plane and solve for the change in u and v
+    pub fn set_footprint(&mut self, ray : &Ray) {
+        let Some(differentials) = ray.differentials else {
+            return;
+        };
+        let level = dot(&self.normal, &self.point);
+        let offset_x = dot(&self.normal, &differentials.x_direction);
+        let offset_y = dot(&self.normal, &differentials.y_direction);
+        if offset_x == 0.0 || offset_y == 0.0 {
+            return;
+        }
+        let t_x = (level - dot(&self.normal, &differentials.x_origin)) / offset_x;
+        let t_y = (level - dot(&self.normal, &differentials.y_origin)) / offset_y;
+        let dpdx = differentials.x_origin + t_x * differentials.x_direction - self.point;
+        let dpdy = differentials.y_origin + t_y * differentials.y_direction - self.point;
+        // Least squares in the tangents
+        let a = dot(&self.dpdu, &self.dpdu);
+        let b = dot(&self.dpdu, &self.dpdv);
+        let c = dot(&self.dpdv, &self.dpdv);
+        let determinant = a * c - b * b;
+        if determinant.abs() < 1e-12 {
+            return;
+        }
+        let solve = |dp : &Vec3| {
+            let (pu, pv) = (dot(&self.dpdu, dp), dot(&self.dpdv, dp));
+            ((c * pu - b * pv) / determinant, (a * pv - b * pu) / determinant)
+        };
+        let (dudx, dvdx) = solve(&dpdx);
+        let (dudy, dvdy) = solve(&dpdy);
+        self.footprint = Some(Footprint { dudx, dvdx, dudy, dvdy });
+    }
+
+    pub fn texture_value(&self, texture : &dyn Texture) -> Colour {
+        texture.value_at_hit(self)
+    }
 }
 
 pub struct Sphere<'mat> {
@@ -67,7 +146,7 @@ impl<'mat> Sphere<'mat> {
 }
 
 impl<'mat> Hittable for Sphere<'mat> {
-    fn hit(&self, ray: &Ray, ray_t : &Interval, _rng : &mut ThreadRng) -> Option<HitRecord<'mat>> {
+    fn hit(&self, ray: &Ray, ray_t : &Interval, rng : &mut ThreadRng) -> Option<HitRecord<'mat>> {
         let oc = self.center - ray.origin;
         // Quad formula
         let a = ray.direction.length_squared();
@@ -78,22 +157,22 @@ impl<'mat> Hittable for Sphere<'mat> {
             return None;
         }
         let sqrtd = discriminant.sqrt();
-        // Find nearest root in range
-        let mut root = (h-sqrtd) / a;
-        if !ray_t.surrounds(root) {
-            root = (h+sqrtd) / a;
+        // Find nearest root in range where the surface is not cut out
+        for root in [(h-sqrtd) / a, (h+sqrtd) / a] {
             if !ray_t.surrounds(root) {
-                return None;
+                continue;
             }
-        }
-        let point = ray.at(root);
-        let normal = (point - self.center) / self.radius;
-        let theta = (-normal.y()).acos();
-        let phi = f64::atan2(-normal.z(), normal.x());
-        let u = phi / (2.0 * PI) + 0.5;
-        let v = theta / PI;
-        Some (
-            HitRecord::new(
+            let point = ray.at(root);
+            let normal = (point - self.center) / self.radius;
+            let theta = (-normal.y()).acos();
+            let phi = f64::atan2(-normal.z(), normal.x());
+            let u = phi / (2.0 * PI) + 0.5;
+            let v = theta / PI;
+            // Derivatives of the point on the sphere in terms of phi and theta, cross to the outward normal
+            let (sin_th, cos_th) = (theta.sin(), theta.cos());
+            let dpdu = 2.0 * PI * self.radius * Vec3::new(-sin_th * phi.sin(), 0.0, -sin_th * phi.cos());
+            let dpdv = PI * self.radius * Vec3::new(cos_th * phi.cos(), sin_th, -cos_th * phi.sin());
+            let mut record = HitRecord::new(
                 &point,
                 root,
                 ray,
@@ -101,8 +180,13 @@ impl<'mat> Hittable for Sphere<'mat> {
                 u,
                 v,
                 self.material
-            )
-        )
+            );
+            record.set_tangents(&dpdu, &dpdv);
+            if !self.material.cutout(rng, &record) {
+                return Some(record);
+            }
+        }
+        None
     }
 
     fn bounding_box(&self) -> AABB {
@@ -127,7 +211,7 @@ pub struct Quadrilateral<'mat> {
 
 impl<'mat> Quadrilateral<'mat> {
     pub fn new(corner : &Point3, u : &Vec3, v : &Vec3, material : &'mat (dyn Material + 'mat)) -> Quadrilateral<'mat> {
-        let bound1 = AABB::from_points(&corner, &(corner+u));
+        let bound1 = AABB::from_points(corner, &(corner+u));
         let bound2 = AABB::from_points(&(corner+v), &(corner+u+v));
         let mut bound = bound1.union(&bound2);
         let c = cross(u, v);
@@ -150,7 +234,7 @@ impl<'mat> Quadrilateral<'mat> {
 }
 
 impl<'mat> Hittable for Quadrilateral<'mat> {
-    fn hit(&self, ray: &Ray, ray_t: &Interval, _rng : &mut ThreadRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord<'_>> {
         let denom = dot(&self.normal, &ray.direction);
         if denom.abs() < 1e-8 {
             return None; // Par
@@ -168,7 +252,7 @@ impl<'mat> Hittable for Quadrilateral<'mat> {
             return None;
         }
         // Return result
-        Some(HitRecord::new(
+        let mut record = HitRecord::new(
             &intersection,
             t,
             ray,
@@ -176,7 +260,12 @@ impl<'mat> Hittable for Quadrilateral<'mat> {
             u,
             v,
             self.material
-        ))
+        );
+        record.set_tangents(&self.u, &self.v);
+        if self.material.cutout(rng, &record) {
+            return None;
+        }
+        Some(record)
     }
 
     fn bounding_box(&self) -> AABB {
@@ -202,7 +291,7 @@ impl<'a> HittableList<'a> {
 }
 
 impl<'a> Hittable for HittableList<'a> {
-    fn hit(&self, ray: &Ray, ray_t : &Interval, rng : &mut ThreadRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, ray_t : &Interval, rng : &mut ThreadRng) -> Option<HitRecord<'_>> {
         let mut best_hit : Option<HitRecord> = None;
         let mut current_best = ray_t.max;
         for object in self.objects.iter() {
@@ -270,7 +359,7 @@ impl<'a, 'tex> ConstantVolume<'a, 'tex> {
 }
 
 impl<'a, 'tex> Hittable for ConstantVolume<'a, 'tex> {
-    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord<'_>> {
         if let Some(hit1) = self.boundary.hit(ray, &Interval::universe(), rng) {
             if let Some(hit2) = self.boundary.hit(ray, &Interval::new(hit1.t + 0.001, f64::MAX), rng) {
                 let hit_t = Interval::new(hit1.t, hit2.t);
@@ -307,37 +396,141 @@ impl<'a, 'tex> Hittable for ConstantVolume<'a, 'tex> {
     }
 }
 
+// Closed boundary filled with a subsurface material, hits on the boundary use that material
+pub struct SubsurfaceObject<'a> {
+    material : Subsurface<'a>
+}
+
+impl<'a> SubsurfaceObject<'a> {
+    pub fn new(material : Subsurface<'a>) -> SubsurfaceObject<'a> {
+        SubsurfaceObject { material }
+    }
+}
+
+impl<'a> Hittable for SubsurfaceObject<'a> {
+    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord<'_>> {
+        let mut record = self.material.boundary().hit(ray, ray_t, rng)?;
+        record.material = &self.material;
+        Some(record)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.material.boundary().bounding_box()
+    }
+}
+
+// Moves by direction each unit of time from where it is at time zero, in the same time as the camera's shutter.
+// The bounding box covers the object over the shutter it is made with
 pub struct MovingObject<'a> {
     direction : Vec3,
-    object : Box<dyn Hittable + 'a>
+    object : Box<dyn Hittable + 'a>,
+    bounding_box : AABB
 }
 
 impl<'a> MovingObject<'a> {
-    pub fn new(direction : &Vec3, object : Box<dyn Hittable + 'a>) -> MovingObject<'a> {
+    pub fn new(direction : &Vec3, shutter : &Interval, object : Box<dyn Hittable + 'a>) -> MovingObject<'a> {
+        let start = object.bounding_box();
+        let bounding_box = start.translate(&(shutter.min * direction)).union(&start.translate(&(shutter.max * direction)));
         MovingObject {
             direction : *direction,
-            object
+            object,
+            bounding_box
         }
     }
 }
 
 impl<'a> Hittable for MovingObject<'a> {
-    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord<'_>> {
         let shift = self.direction*ray.time;
-        let moved_ray = Ray::new(&(ray.origin - shift), &ray.direction, 0.0);
+        let moved_ray = Ray::new(&(ray.origin - shift), &ray.direction, ray.time);
         self.object.hit(&moved_ray, ray_t, rng).map(
             |record| {
                 let mut record_mut = record;
-                record_mut.point = record_mut.point + shift;
+                record_mut.point += shift;
                 record_mut
             }
         )
     }
 
     fn bounding_box(&self) -> AABB {
-        let start = self.object.bounding_box();
-        let end = start.translate(&self.direction);
-        start.union(&end)
+        self.bounding_box.clone()
+    }
+}
+
+// Steps between key times when sweeping the bounding box over the shutter
+const SWEEP_STEPS : usize = 16;
+
+pub struct Animated<'a> {
+    animation : Animation,
+    object : Box<dyn Hittable + 'a>,
+    bounding_box : AABB
+}
+
+fn sweep_times(animation : &Animation, shutter : &Interval) -> Vec<f64> {
+    let mut stops = vec![shutter.min];
+    stops.extend(animation.key_times().into_iter().filter(|time| shutter.surrounds(*time)));
+    stops.push(shutter.max);
+    let mut times = Vec::new();
+    for pair in stops.windows(2) {
+        for step in 0..SWEEP_STEPS {
+            let s = step as f64 / SWEEP_STEPS as f64;
+            times.push((1.0 - s) * pair[0] + s * pair[1]);
+        }
+    }
+    times.push(shutter.max);
+    times
+}
+
+impl<'a> Animated<'a> {
+    pub fn new(animation : Animation, shutter : &Interval, object : Box<dyn Hittable + 'a>) -> Animated<'a> {
+        let corners = object.bounding_box().points();
+        let mut aabb = AABB::empty();
+        let mut max_step : f64 = 0.0;
+        let mut previous : Option<[Point3; 8]> = None;
+        for time in sweep_times(&animation, shutter) {
+            let transform = animation.at(time);
+            let moved = corners.map(|corner| transform.apply_point(&corner));
+            for point in moved.iter() {
+                aabb.expand(point);
+            }
+            if let Some(last) = previous {
+                for (a, b) in last.iter().zip(moved.iter()) {
+                    max_step = max_step.max((a - b).length());
+                }
+            }
+            previous = Some(moved);
+        }
+        // Corners move on curves between samples so pad by the largest step
+        Animated {
+            animation,
+            object,
+            bounding_box : aabb.grow(max_step)
+        }
+    }
+}
+
+impl<'a> Hittable for Animated<'a> {
+    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord<'_>> {
+        let transform = self.animation.at(ray.time);
+        let object_ray = Ray::new(
+            &transform.inverse_point(&ray.origin),
+            &transform.inverse_vector(&ray.direction),
+            ray.time
+        );
+        self.object.hit(&object_ray, ray_t, rng).map(
+            |record| {
+                let mut record_mut = record;
+                record_mut.point = transform.apply_point(&record_mut.point);
+                record_mut.normal = transform.apply_normal(&record_mut.normal).unit();
+                record_mut.dpdu = transform.apply_vector(&record_mut.dpdu);
+                record_mut.dpdv = transform.apply_vector(&record_mut.dpdv);
+                record_mut
+            }
+        )
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bounding_box.clone()
     }
 }
 
@@ -358,12 +551,12 @@ impl<'a> Translated<'a> {
 }
 
 impl<'a> Hittable for Translated<'a> {
-    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord<'_>> {
         let moved_ray = Ray::new(&(ray.origin - self.direction), &ray.direction, ray.time);
         self.object.hit(&moved_ray, ray_t, rng).map(
             |record| {
                 let mut record_mut = record;
-                record_mut.point = record_mut.point + self.direction;
+                record_mut.point += self.direction;
                 record_mut
             }
         )
@@ -410,7 +603,7 @@ impl<'a> RotateY<'a> {
 }
 
 impl<'a> Hittable for RotateY<'a> {
-    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord<'_>> {
         let r_origin = rotate_y_vec(self.angle_cos, -self.angle_sin, &ray.origin);
         let r_direction = rotate_y_vec(self.angle_cos, -self.angle_sin, &ray.direction);
         let r_ray = Ray::new(&r_origin, &r_direction, ray.time);
@@ -418,6 +611,9 @@ impl<'a> Hittable for RotateY<'a> {
             |record| {
                 let mut record_mut = record;
                 record_mut.point = rotate_y_vec(self.angle_cos, self.angle_sin, &record_mut.point);
+                record_mut.normal = rotate_y_vec(self.angle_cos, self.angle_sin, &record_mut.normal);
+                record_mut.dpdu = rotate_y_vec(self.angle_cos, self.angle_sin, &record_mut.dpdu);
+                record_mut.dpdv = rotate_y_vec(self.angle_cos, self.angle_sin, &record_mut.dpdv);
                 record_mut
             }
         )
@@ -428,6 +624,7 @@ impl<'a> Hittable for RotateY<'a> {
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 pub enum BVH<'a> {
     Empty,
     Leaf(AABB, Box<dyn Hittable + 'a>),
@@ -467,7 +664,7 @@ impl<'a> BVH<'a> {
 }
 
 impl<'a> Hittable for BVH<'a> {
-    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, ray_t: &Interval, rng : &mut ThreadRng) -> Option<HitRecord<'_>> {
         match self {
             BVH::Empty => None,
             BVH::Leaf(aabb, item) => {
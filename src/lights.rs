@@ -0,0 +1,131 @@
+use std::f64::consts::PI;
+use rand::Rng;
+use rand::rngs::ThreadRng;
+use crate::colour::Colour;
+use crate::vec3::{dot, OrthonormalBasis, Point3, UnitVec3, Vec3};
+
+// Light arriving at a point from a single sampled direction
+pub struct LightSample {
+    pub direction : UnitVec3,
+    pub distance : f64,
+    pub radiance : Colour,
+    pub pdf : f64
+}
+
+// Lights with no surface, rays can never hit these so they are only sampled directly
+pub trait Light : Sync {
+    fn sample(&self, rng : &mut ThreadRng, point : &Point3) -> Option<LightSample>;
+}
+
+pub struct PointLight {
+    position : Point3,
+    intensity : Colour
+}
+
+impl PointLight {
+    pub fn new(position : &Point3, intensity : &Colour) -> PointLight {
+        PointLight { position : *position, intensity : *intensity }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, _rng : &mut ThreadRng, point : &Point3) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance_sq = to_light.length_squared();
+        let distance = distance_sq.sqrt();
+        Some(LightSample {
+            direction : to_light / distance,
+            distance,
+            radiance : self.intensity / distance_sq,
+            pdf : 1.0
+        })
+    }
+}
+
+pub struct SpotLight {
+    position : Point3,
+    direction : UnitVec3,
+    intensity : Colour,
+    cos_cone : f64,
+    cos_falloff : f64
+}
+
+impl SpotLight {
+    // Angles in degrees from the spot direction, full intensity inside falloff_angle
+    // fading to nothing at cone_angle
+    pub fn new(position : &Point3, direction : &Vec3, intensity : &Colour, cone_angle : f64, falloff_angle : f64) -> SpotLight {
+        let cos_cone = (cone_angle * PI / 180.0).cos();
+        let cos_falloff = (falloff_angle.min(cone_angle) * PI / 180.0).cos();
+        SpotLight {
+            position : *position,
+            direction : direction.unit(),
+            intensity : *intensity,
+            cos_cone,
+            cos_falloff
+        }
+    }
+
+    fn falloff(&self, cos_th : f64) -> f64 {
+        if cos_th <= self.cos_cone {
+            return 0.0;
+        }
+        if cos_th >= self.cos_falloff {
+            return 1.0;
+        }
+        let s = (cos_th - self.cos_cone) / (self.cos_falloff - self.cos_cone);
+        s * s * (3.0 - 2.0 * s)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, _rng : &mut ThreadRng, point : &Point3) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance_sq = to_light.length_squared();
+        let distance = distance_sq.sqrt();
+        let direction = to_light / distance;
+        let falloff = self.falloff(dot(&-direction, &self.direction));
+        if falloff <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance : falloff * self.intensity / distance_sq,
+            pdf : 1.0
+        })
+    }
+}
+
+// Light from far away like the sun, irradiance is measured facing the light
+pub struct DirectionalLight {
+    to_light : OrthonormalBasis,
+    irradiance : Colour,
+    cos_radius : f64
+}
+
+impl DirectionalLight {
+    // Angular diameter in degrees, zero gives perfectly hard shadows
+    pub fn new(direction : &Vec3, irradiance : &Colour, angular_diameter : f64) -> DirectionalLight {
+        DirectionalLight {
+            to_light : OrthonormalBasis::new(&-direction),
+            irradiance : *irradiance,
+            cos_radius : (0.5 * angular_diameter * PI / 180.0).cos()
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, rng : &mut ThreadRng, _point : &Point3) -> Option<LightSample> {
+        // Uniform over the cone of the disc
+        let cos_th = 1.0 - rng.gen::<f64>() * (1.0 - self.cos_radius);
+        let sin_th = (1.0 - cos_th * cos_th).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let local = Vec3::new(sin_th * phi.cos(), sin_th * phi.sin(), cos_th);
+        Some(LightSample {
+            direction : self.to_light.to_world(&local),
+            distance : f64::MAX,
+            radiance : self.irradiance,
+            pdf : 1.0
+        })
+    }
+}
@@ -12,17 +12,48 @@ mod materials;
 mod aabb;
 mod textures;
 mod perlin;
+mod worley;
+mod quaternion;
+mod animation;
+mod lights;
+mod environment;
+mod sky;
+mod microfacet;
+mod spectrum;
+mod principled;
+mod mipmap;
+mod assets;
+mod bump;
+mod patterns;
+mod nodes;
+mod projection;
 
 use std::env::args;
+use std::path::PathBuf;
 use rand::rngs::ThreadRng;
 use rand::{thread_rng, Rng};
 use crate::aabb::AABB;
+use crate::assets::AssetResolver;
+use crate::animation::Animation;
 use crate::camera::Camera;
-use crate::colour::{random_colour_light, random_colour_sq, Colour};
-use crate::hittables::{make_box, ConstantVolume, HittableList, MovingObject, Quadrilateral, RotateY, Sphere, Translated, BVH};
-use crate::materials::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
-use crate::textures::{Checker, ImageTexture, MarbleTexture, SolidColour, TextureWorld};
-use crate::vec3::{Point3, Vec3};
+use crate::colour::{random_colour_light, random_colour_sq, Colour, ColourSpace};
+use crate::environment::EnvironmentMap;
+use crate::sky::{PreethamSky, DAYLIGHT_INTENSITY};
+use crate::lights::{DirectionalLight, PointLight, SpotLight};
+use crate::hittables::{make_box, Animated, ConstantVolume, HittableList, MovingObject, Quadrilateral, RotateY, Sphere, SubsurfaceObject, Translated, BVH};
+use crate::materials::{absorption_for, AlphaMask, Coated, Conductor, Cutout, Dielectric, DiffuseLight, Lambertian, Material, Metal, MixMaterial, OrenNayar, RefractiveIndex, RoughDielectric, Subsurface, ThinFilm, Velvet};
+use crate::principled::Principled;
+use crate::mipmap::{Filter, MipFilter, Wrap};
+use crate::spectrum::blackbody;
+use crate::bump::BumpMapped;
+use crate::textures::{CellularTexture, Checker, Fractal, FractalTexture, ImageTexture, MarbleTexture, Scalar, SolidColour, Texture, TextureWorld, WarpedTexture};
+use crate::worley::CellFeature;
+use crate::perlin::Perlin;
+use crate::nodes::{Blend, ClampTexture, HsvTexture, InvertTexture, MixTexture, RampTexture, TransformTexture};
+use crate::projection::ProjectedTexture;
+use crate::patterns::{BrickTexture, GradientTexture, UvChecker, UvGrid, UvTestPattern, WoodTexture};
+use crate::quaternion::Quaternion;
+use crate::vec3::{Axis3, Point3, Vec3};
 
 
 fn random_material<'tex>(rng : &mut ThreadRng, textures : &'tex TextureWorld<'tex>) -> (bool, Box<dyn Material + 'tex>) {
@@ -55,7 +86,7 @@ fn many_spheres_scene(image_file : &str, dark_mode : bool) {
     } else {
         Colour::new(0.7, 0.8, 1.0)
     };
-    let camera = Camera::new(
+    let mut camera = Camera::new(
         &Point3::new(13.0, 2.0, 3.0),
         &Point3::new(0.0, 0.0, 0.0),
         &Vec3::new(0.0, 1.0, 0.0),
@@ -68,6 +99,9 @@ fn many_spheres_scene(image_file : &str, dark_mode : bool) {
         0.6,
         background
     );
+    if !dark_mode {
+        camera.set_environment(Box::new(PreethamSky::new(40.0, 110.0, 3.0, DAYLIGHT_INTENSITY)));
+    }
 
     let small_sphere_num_side = 11i64;
     let small_sphere_num_total = 4 * small_sphere_num_side * small_sphere_num_side;
@@ -120,7 +154,7 @@ fn many_spheres_scene(image_file : &str, dark_mode : bool) {
         }
     }
     for (center, mat) in centers.iter().zip(small_sphere_materials.iter()) {
-        let sphere = Box::new(Sphere::new(&center, 0.2, mat.as_ref()));
+        let sphere = Box::new(Sphere::new(center, 0.2, mat.as_ref()));
         world.add(sphere);
     }
 
@@ -201,10 +235,10 @@ fn bouncing_spheres(image_file : &str) {
         }
     }
     for (center, (moving, mat)) in centers.iter().zip(small_sphere_materials.iter()) {
-        let sphere = Box::new(Sphere::new(&center, 0.2, mat.as_ref()));
+        let sphere = Box::new(Sphere::new(center, 0.2, mat.as_ref()));
         if *moving {
             let jump = Vec3::new(0.0, rng.gen::<f64>() * 0.4, 0.0);
-            world.add(Box::new(MovingObject::new(&jump, sphere)))
+            world.add(Box::new(MovingObject::new(&jump, &camera.shutter(), sphere)))
         } else {
             world.add(sphere);
         }
@@ -256,7 +290,7 @@ fn checkered_spheres(image_file : &str) {
     camera.render(image_file, &world_ordered);
 }
 
-fn earth(image_file : &str) {
+fn earth(image_file : &str, assets : &AssetResolver) {
     // Camera
     let aspect_ratio = 16.0 / 9.0;
     let image_width : u32 = 400;
@@ -277,7 +311,8 @@ fn earth(image_file : &str) {
         Colour::new(0.7, 0.8, 1.0)
     );
     // Make image
-    let globe_texture = ImageTexture::load("earthmap.jpg").expect("Could not load texture");
+    let mut globe_texture = ImageTexture::load(assets, "earthmap.jpg", ColourSpace::Srgb).expect("Could not load texture");
+    globe_texture.set_filter(Filter::Bilinear, MipFilter::Trilinear);
     let globe_material = Lambertian::new(&globe_texture);
     // Make world
     let mut world = HittableList::new();
@@ -622,7 +657,7 @@ fn cornell_smoke(image_file : &str) {
     camera.render(image_file, &world_ordered);
 }
 
-fn final_scene(image_file : &str, image_width : u32, samples_per_pixel: u32, max_depth : u8) {
+fn final_scene(image_file : &str, assets : &AssetResolver, image_width : u32, samples_per_pixel: u32, max_depth : u8) {
     // Camera
     let aspect_ratio = 1.0;
     let fov: f64 = 40.0;
@@ -653,7 +688,7 @@ fn final_scene(image_file : &str, image_width : u32, samples_per_pixel: u32, max
     let light_material = DiffuseLight::new(&light_colour);
     let sphere_colour = SolidColour::new(&Colour::new(0.7,0.3,0.1));
     let sphere_material = Lambertian::new(&sphere_colour);
-    let globe_texture = ImageTexture::load("earthmap.jpg").expect("Could not load texture");
+    let globe_texture = ImageTexture::load(assets, "earthmap.jpg", ColourSpace::Srgb).expect("Could not load texture");
     let globe_material = Lambertian::new(&globe_texture);
     let marble_texture = MarbleTexture::new(&mut rng, 0.2);
     let marble_material = Lambertian::new(&marble_texture);
@@ -691,6 +726,7 @@ fn final_scene(image_file : &str, image_width : u32, samples_per_pixel: u32, max
     // Spheres
     world.add(Box::new(MovingObject::new(
         &Vec3::new(0.0, 0.0, 30.0),
+        &camera.shutter(),
         Box::new(Sphere::new(&Point3::new(400.0, 400.0, 200.0), 50.0, &sphere_material))
     )));
     world.add(Box::new(
@@ -738,22 +774,1387 @@ fn final_scene(image_file : &str, image_width : u32, samples_per_pixel: u32, max
     camera.render(image_file, &world_ordered);
 }
 
-fn main() {
-    let scene = args().into_iter().nth(1).unwrap_or("many_spheres_dark".to_string());
+fn animated(image_file : &str, frame : u32) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 200;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 3.0, 12.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::new(0.7, 0.8, 1.0)
+    );
+    camera.set_frame(frame, 24.0, 180.0);
+    let shutter = camera.shutter();
+
+    // Make materials
+    let ground_light = SolidColour::new(&Colour::new(0.9,0.9,0.9));
+    let ground_dark = SolidColour::new(&Colour::new(0.2,0.3,0.1));
+    let ground_texture = Checker::new(0.5, &ground_dark, &ground_light);
+    let ground_material = Lambertian::new(&ground_texture);
+    let red = SolidColour::new(&Colour::new(0.65, 0.05, 0.05));
+    let red_material = Lambertian::new(&red);
+    let blue = SolidColour::new(&Colour::new(0.1, 0.2, 0.7));
+    let blue_material = Lambertian::new(&blue);
+    let steel = SolidColour::new(&Colour::new(0.8, 0.8, 0.9));
+    let steel_material = Metal::new(&steel, 0.1);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+
+    // Box spinning a full turn every second
+    let mut spin = Animation::new();
+    let axis = Vec3::new(0.0, 1.0, 0.0);
+    for quarter in 0..=8 {
+        let time = quarter as f64 * 0.25;
+        spin.add_rotation(time, &Quaternion::from_axis_angle(&axis, 90.0 * quarter as f64));
+    }
+    spin.add_translation(0.0, &Vec3::new(-3.0, 1.0, 0.0));
+    let cube = Box::new(make_box(&Point3::new(-0.8, -0.8, -0.8), &Point3::new(0.8, 0.8, 0.8), &red_material));
+    world.add(Box::new(Animated::new(spin, &shutter, cube)));
+
+    // Sphere accelerating from rest
+    let mut fall = Animation::new();
+    for step in 0..=8 {
+        let time = step as f64 * 0.25;
+        fall.add_translation(time, &Vec3::new(0.0, 4.0 - time * time, 0.0));
+    }
+    let ball = Box::new(Sphere::new(&Point3::zero(), 0.8, &steel_material));
+    world.add(Box::new(Animated::new(fall, &shutter, ball)));
+
+    // Sphere pulsing in size
+    let mut pulse = Animation::new();
+    pulse.add_translation(0.0, &Vec3::new(3.0, 1.0, 0.0));
+    pulse.add_scale(0.0, &Vec3::new(1.0, 1.0, 1.0));
+    pulse.add_scale(0.5, &Vec3::new(1.4, 0.7, 1.4));
+    pulse.add_scale(1.0, &Vec3::new(1.0, 1.0, 1.0));
+    let blob = Box::new(Sphere::new(&Point3::zero(), 0.8, &blue_material));
+    world.add(Box::new(Animated::new(pulse, &shutter, blob)));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn stage_lights(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 100;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(3.0, 3.0, 13.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::new(0.02, 0.02, 0.03)
+    );
+    // Low warm sun, a cool spotlight on the glass and a small fill light
+    camera.add_light(Box::new(DirectionalLight::new(
+        &Vec3::new(-1.0, -0.6, -0.4),
+        &Colour::new(1.0, 0.85, 0.6),
+        0.53
+    )));
+    camera.add_light(Box::new(SpotLight::new(
+        &Point3::new(0.0, 6.0, 2.0),
+        &Vec3::new(0.0, -6.0, -2.0),
+        &Colour::new(30.0, 35.0, 50.0),
+        18.0,
+        12.0
+    )));
+    camera.add_light(Box::new(PointLight::new(
+        &Point3::new(4.0, 2.5, 3.0),
+        &Colour::new(3.0, 3.0, 3.0)
+    )));
+
+    // Make materials
+    let mut rng = thread_rng();
+    let marble_texture = MarbleTexture::new(&mut rng, 4.0);
+    let marble_material = Lambertian::new(&marble_texture);
+    let ground_colour = SolidColour::new(&Colour::new(0.5, 0.5, 0.5));
+    let ground_material = Lambertian::new(&ground_colour);
+    let metal_colour = SolidColour::new(&Colour::new(0.7, 0.6, 0.5));
+    let metal = Metal::new(&metal_colour, 0.0);
+    let glass = Dielectric::new(1.5);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &glass)));
+    world.add(Box::new(Sphere::new(&Point3::new(-4.0, 1.0, 0.0), 1.0, &marble_material)));
+    world.add(Box::new(Sphere::new(&Point3::new(4.0, 1.0, 0.0), 1.0, &metal)));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn environment_lit(image_file : &str, assets : &AssetResolver) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 100;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(3.0, 3.0, 13.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::zero()
+    );
+    let environment = EnvironmentMap::load(assets, "neon.hdr", 0.0, 1.0).expect("Could not load environment");
+    camera.set_environment(Box::new(environment));
+
+    // Make materials
+    let ground_colour = SolidColour::new(&Colour::new(0.5, 0.5, 0.5));
+    let ground_material = Lambertian::new(&ground_colour);
+    let diffuse_colour = SolidColour::new(&Colour::new(0.4, 0.2, 0.1));
+    let diffuse = Lambertian::new(&diffuse_colour);
+    let metal_colour = SolidColour::new(&Colour::new(0.7, 0.6, 0.5));
+    let metal = Metal::new(&metal_colour, 0.0);
+    let glass = Dielectric::new(1.5);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &glass)));
+    world.add(Box::new(Sphere::new(&Point3::new(-4.0, 1.0, 0.0), 1.0, &diffuse)));
+    world.add(Box::new(Sphere::new(&Point3::new(4.0, 1.0, 0.0), 1.0, &metal)));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn metals(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 100;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 3.0, 14.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::zero()
+    );
+    camera.set_environment(Box::new(PreethamSky::new(30.0, 60.0, 3.0, DAYLIGHT_INTENSITY)));
+
+    // Make materials
+    let ground_light = SolidColour::new(&Colour::new(0.9,0.9,0.9));
+    let ground_dark = SolidColour::new(&Colour::new(0.2,0.3,0.1));
+    let ground_texture = Checker::new(0.5, &ground_dark, &ground_light);
+    let ground_material = Lambertian::new(&ground_texture);
+    let metals = [
+        Conductor::gold(0.1),
+        Conductor::silver(0.0),
+        Conductor::copper(0.3),
+        Conductor::aluminium(0.5),
+        Conductor::iron(0.2)
+    ];
+    // Brushed around the sphere's axis, smooth along the grain and rough across it
+    let brushed = Conductor::new(&Colour::new(1.657, 0.880, 0.521), &Colour::new(9.224, 6.270, 4.837), 0.1, 0.5);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+    for (i, metal) in metals.iter().enumerate() {
+        let x = 2.2 * (i as f64 - 2.0);
+        world.add(Box::new(Sphere::new(&Point3::new(x, 1.0, 0.0), 1.0, metal)));
+    }
+    world.add(Box::new(Sphere::new(&Point3::new(1.1, 1.4, -3.0), 1.4, &brushed)));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn frosted_glass(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 200;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 2.0, 14.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::zero()
+    );
+    camera.set_environment(Box::new(PreethamSky::new(45.0, 80.0, 3.0, DAYLIGHT_INTENSITY)));
+
+    // Make materials
+    let ground_light = SolidColour::new(&Colour::new(0.9,0.9,0.9));
+    let ground_dark = SolidColour::new(&Colour::new(0.2,0.3,0.1));
+    let ground_texture = Checker::new(0.5, &ground_dark, &ground_light);
+    let ground_material = Lambertian::new(&ground_texture);
+    let glasses = [
+        RoughDielectric::new(1.5, 0.0),
+        RoughDielectric::new(1.5, 0.1),
+        RoughDielectric::new(1.5, 0.3),
+        RoughDielectric::new(1.5, 0.6)
+    ];
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-8.0, 0.0, -3.0),
+        &Vec3::new(16.0, 0.0, 0.0),
+        &Vec3::new(0.0, 6.0, 0.0),
+        &ground_material
+    )));
+    for (i, glass) in glasses.iter().enumerate() {
+        let x = 2.5 * (i as f64 - 1.5);
+        world.add(Box::new(Sphere::new(&Point3::new(x, 1.0, 0.0), 1.0, glass)));
+    }
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn coloured_glass(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 200;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 3.0, 14.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::zero()
+    );
+    camera.set_environment(Box::new(PreethamSky::new(45.0, 80.0, 3.0, DAYLIGHT_INTENSITY)));
+
+    // Make materials
+    let ground_colour = SolidColour::new(&Colour::new(0.8, 0.8, 0.8));
+    let ground_material = Lambertian::new(&ground_colour);
+    // Same tint so thicker glass looks darker
+    let amber = absorption_for(&Colour::new(0.9, 0.55, 0.2), 1.0);
+    let amber_glass = Dielectric::absorbing(1.5, &amber);
+    let green = absorption_for(&Colour::new(0.7, 0.95, 0.8), 0.5);
+    let green_glass = Dielectric::absorbing(1.5, &green);
+    let frosted_blue = RoughDielectric::absorbing(1.5, 0.2, &absorption_for(&Colour::new(0.3, 0.5, 0.9), 1.0));
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+    world.add(Box::new(Sphere::new(&Point3::new(-4.0, 0.3, 1.0), 0.3, &amber_glass)));
+    world.add(Box::new(Sphere::new(&Point3::new(-3.0, 0.6, 0.0), 0.6, &amber_glass)));
+    world.add(Box::new(Sphere::new(&Point3::new(-1.0, 1.2, -1.0), 1.2, &amber_glass)));
+    // Thick slab shows green edges
+    world.add(Box::new(Translated::new(
+        &Vec3::new(2.0, 0.0, 0.0),
+        Box::new(RotateY::new(30.0, Box::new(make_box(
+            &Point3::new(-1.5, 0.0, -0.2), &Point3::new(1.5, 2.0, 0.2), &green_glass
+        ))))
+    )));
+    world.add(Box::new(Sphere::new(&Point3::new(4.5, 1.0, 1.0), 1.0, &frosted_blue)));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn dispersion(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 400;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 2.0, 12.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::new(0.02, 0.02, 0.03)
+    );
+    camera.set_spectral(true);
+
+    // Make materials
+    let white = SolidColour::new(&Colour::new(0.8, 0.8, 0.8));
+    let black = SolidColour::new(&Colour::new(0.05, 0.05, 0.05));
+    let checker = Checker::new(0.5, &white, &black);
+    let ground_material = Lambertian::new(&checker);
+    let light_colour = SolidColour::new(&Colour::new(20.0, 20.0, 20.0));
+    let light = DiffuseLight::new(&light_colour);
+    let crown = Dielectric::dispersive(RefractiveIndex::crown_glass(), &Colour::zero());
+    let flint = Dielectric::dispersive(RefractiveIndex::flint_glass(), &Colour::zero());
+    let diamond = Dielectric::dispersive(RefractiveIndex::diamond(), &Colour::zero());
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+    // Thin bright strips behind the spheres show the coloured fringes
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-6.0, 0.5, -4.0), &Vec3::new(12.0, 0.0, 0.0), &Vec3::new(0.0, 0.15, 0.0), &light
+    )));
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-6.0, 2.5, -4.0), &Vec3::new(12.0, 0.0, 0.0), &Vec3::new(0.0, 0.15, 0.0), &light
+    )));
+    world.add(Box::new(Sphere::new(&Point3::new(-2.6, 1.0, 0.0), 1.0, &crown)));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &flint)));
+    world.add(Box::new(Sphere::new(&Point3::new(2.6, 1.0, 0.0), 1.0, &diamond)));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn principled(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 200;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 3.0, 14.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::zero()
+    );
+    camera.set_environment(Box::new(PreethamSky::new(35.0, 70.0, 3.0, DAYLIGHT_INTENSITY)));
+
+    // Make materials
+    let ground_colour = SolidColour::new(&Colour::new(0.5, 0.5, 0.5));
+    let ground_material = Principled::new(&ground_colour, Scalar::Value(0.0), Scalar::Value(0.8));
+    let red = SolidColour::new(&Colour::new(0.8, 0.1, 0.1));
+    let gold = SolidColour::new(&Colour::new(1.0, 0.78, 0.34));
+    let blue = SolidColour::new(&Colour::new(0.1, 0.2, 0.7));
+    let velvet = SolidColour::new(&Colour::new(0.4, 0.05, 0.3));
+    let pale = SolidColour::new(&Colour::new(0.9, 1.0, 0.95));
+    let plastic = Principled::new(&red, Scalar::Value(0.0), Scalar::Value(0.3));
+    let metal = Principled::new(&gold, Scalar::Value(1.0), Scalar::Value(0.25));
+    let mut car_paint = Principled::new(&blue, Scalar::Value(0.5), Scalar::Value(0.5));
+    car_paint.set_clearcoat(Scalar::Value(1.0), Scalar::Value(0.05));
+    let mut cloth = Principled::new(&velvet, Scalar::Value(0.0), Scalar::Value(1.0));
+    cloth.set_sheen(Scalar::Value(1.0));
+    cloth.set_specular(Scalar::Value(0.2), Scalar::Value(0.5));
+    let mut glass = Principled::new(&pale, Scalar::Value(0.0), Scalar::Value(0.05));
+    glass.set_transmission(Scalar::Value(1.0), Scalar::Value(1.5));
+    // Metal flakes painted on in a pattern
+    let black = SolidColour::new(&Colour::zero());
+    let white = SolidColour::new(&Colour::new(1.0, 1.0, 1.0));
+    let pattern = Checker::new(0.25, &black, &white);
+    let patterned = Principled::new(&gold, Scalar::Texture(&pattern), Scalar::Value(0.4));
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+    let materials : [&dyn Material; 5] = [&plastic, &metal, &car_paint, &cloth, &glass];
+    for (i, material) in materials.iter().enumerate() {
+        let x = 2.2 * (i as f64 - 2.0);
+        world.add(Box::new(Sphere::new(&Point3::new(x, 1.0, 0.0), 1.0, *material)));
+    }
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -3.0), 1.0, &patterned)));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn layered(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 200;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 3.0, 14.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::zero()
+    );
+    camera.set_environment(Box::new(PreethamSky::new(35.0, 70.0, 3.0, DAYLIGHT_INTENSITY)));
+    camera.add_light(Box::new(PointLight::new(&Point3::new(-4.0, 6.0, 6.0), &Colour::new(60.0, 60.0, 60.0))));
+
+    // Make materials
+    let mut rng = thread_rng();
+    let ground_colour = SolidColour::new(&Colour::new(0.5, 0.5, 0.5));
+    let ground_material = Lambertian::new(&ground_colour);
+    let marble = MarbleTexture::new(&mut rng, 4.0);
+    let marble_material = Lambertian::new(&marble);
+    // Amber varnish over stone
+    let amber = absorption_for(&Colour::new(0.9, 0.6, 0.3), 0.1);
+    let varnished = Coated::new(&marble_material, 1.5, 0.0, 0.05, &amber);
+    // Rust showing through paint where the noise is bright
+    let paint_colour = SolidColour::new(&Colour::new(0.1, 0.3, 0.6));
+    let paint_base = Lambertian::new(&paint_colour);
+    let paint = Coated::new(&paint_base, 1.5, 0.1, 0.0, &Colour::zero());
+    let rust_colour = SolidColour::new(&Colour::new(0.45, 0.2, 0.08));
+    let rust = Lambertian::new(&rust_colour);
+    let rusty_paint = MixMaterial::new(&paint, &rust, Scalar::Texture(&marble));
+    // Lacquer over metals, the mirror base can only be reached by scattering
+    let gold = Conductor::gold(0.3);
+    let lacquered_gold = Coated::new(&gold, 1.5, 0.0, 0.0, &Colour::zero());
+    let mirror_colour = SolidColour::new(&Colour::new(0.9, 0.9, 0.9));
+    let mirror = Metal::new(&mirror_colour, 0.0);
+    let red_lacquer = absorption_for(&Colour::new(0.9, 0.2, 0.2), 0.1);
+    let red_mirror = Coated::new(&mirror, 1.5, 0.0, 0.05, &red_lacquer);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+    let materials : [&dyn Material; 4] = [&varnished, &rusty_paint, &lacquered_gold, &red_mirror];
+    for (i, material) in materials.iter().enumerate() {
+        let x = 2.4 * (i as f64 - 1.5);
+        world.add(Box::new(Sphere::new(&Point3::new(x, 1.0, 0.0), 1.0, *material)));
+    }
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn rough_diffuse(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 200;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 3.0, 14.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::new(0.05, 0.05, 0.08)
+    );
+    // Light from behind the camera shows the flat look of rough surfaces
+    camera.add_light(Box::new(PointLight::new(&Point3::new(0.0, 4.0, 16.0), &Colour::new(200.0, 200.0, 200.0))));
+    camera.add_light(Box::new(SpotLight::new(
+        &Point3::new(6.0, 6.0, -6.0), &Vec3::new(-1.0, -1.0, 1.0), &Colour::new(150.0, 150.0, 170.0), 40.0, 30.0
+    )));
+
+    // Make materials
+    let ground_colour = SolidColour::new(&Colour::new(0.5, 0.5, 0.5));
+    let ground_material = OrenNayar::new(&ground_colour, 30.0);
+    let clay_colour = SolidColour::new(&Colour::new(0.7, 0.4, 0.25));
+    let smooth_clay = Lambertian::new(&clay_colour);
+    let clay = OrenNayar::new(&clay_colour, 20.0);
+    let concrete_colour = SolidColour::new(&Colour::new(0.6, 0.6, 0.58));
+    let concrete = OrenNayar::new(&concrete_colour, 60.0);
+    let velvet_colour = SolidColour::new(&Colour::new(0.3, 0.02, 0.1));
+    let velvet_sheen = SolidColour::new(&Colour::new(1.0, 0.5, 0.7));
+    let velvet = Velvet::new(&velvet_colour, &velvet_sheen, 0.4);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+    let materials : [&dyn Material; 4] = [&smooth_clay, &clay, &concrete, &velvet];
+    for (i, material) in materials.iter().enumerate() {
+        let x = 2.4 * (i as f64 - 1.5);
+        world.add(Box::new(Sphere::new(&Point3::new(x, 1.0, 0.0), 1.0, *material)));
+    }
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn subsurface(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 200;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 3.0, 14.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::zero()
+    );
+    camera.set_environment(Box::new(PreethamSky::new(20.0, 100.0, 3.0, 0.02)));
+    // Light from behind glows through the thin parts
+    camera.add_light(Box::new(PointLight::new(&Point3::new(0.0, 4.0, -5.0), &Colour::new(80.0, 70.0, 60.0))));
+
+    // Make materials
+    let ground_colour = SolidColour::new(&Colour::new(0.5, 0.5, 0.5));
+    let ground_material = Lambertian::new(&ground_colour);
+    // Boundaries only give the shape, their own material is never used
+    let skin = Subsurface::from_albedo(
+        Box::new(Sphere::new(&Point3::new(-3.6, 1.0, 0.0), 1.0, &ground_material)),
+        &Colour::new(0.85, 0.55, 0.45),
+        &Colour::new(0.4, 0.15, 0.08),
+        1.4
+    );
+    let wax = Subsurface::from_albedo(
+        Box::new(Sphere::new(&Point3::new(-1.2, 1.0, 0.0), 1.0, &ground_material)),
+        &Colour::new(0.9, 0.8, 0.5),
+        &Colour::new(0.5, 0.3, 0.1),
+        1.45
+    );
+    let marble = Subsurface::from_albedo(
+        Box::new(make_box(&Point3::new(0.3, 0.0, -0.9), &Point3::new(2.1, 1.8, 0.9), &ground_material)),
+        &Colour::new(0.9, 0.9, 0.88),
+        &Colour::new(0.1, 0.1, 0.1),
+        1.5
+    );
+    // Milk given as scattering and absorption coefficients
+    let milk = Subsurface::new(
+        Box::new(Sphere::new(&Point3::new(3.6, 1.0, 0.0), 1.0, &ground_material)),
+        &Colour::new(25.0, 28.0, 30.0),
+        &Colour::new(0.1, 0.2, 0.4),
+        1.35
+    );
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+    for material in [skin, wax, marble, milk] {
+        world.add(Box::new(SubsurfaceObject::new(material)));
+    }
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn thin_film(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 200;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 3.0, 14.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::zero()
+    );
+    camera.set_environment(Box::new(PreethamSky::new(35.0, 70.0, 3.0, DAYLIGHT_INTENSITY)));
+
+    // Make materials
+    let mut rng = thread_rng();
+    let ground_light = SolidColour::new(&Colour::new(0.9, 0.9, 0.9));
+    let ground_dark = SolidColour::new(&Colour::new(0.1, 0.1, 0.1));
+    let ground_texture = Checker::new(0.5, &ground_dark, &ground_light);
+    let ground_material = Lambertian::new(&ground_texture);
+    // Soap film swirling between thicknesses over air
+    let swirl = MarbleTexture::new(&mut rng, 2.0);
+    let mut bubble = Dielectric::new(1.0);
+    bubble.set_thin_film(ThinFilm::textured(&swirl, 250.0, 750.0, 1.33));
+    // Quarter wave anti-reflection coating on a lens
+    let mut lens = Dielectric::new(1.5);
+    lens.set_thin_film(ThinFilm::new(100.0, 1.38));
+    // Oxide layer grown on steel by heating
+    let mut tempered = Conductor::iron(0.1);
+    tempered.set_thin_film(ThinFilm::new(300.0, 2.2));
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+    world.add(Box::new(Sphere::new(&Point3::new(-2.4, 1.0, 0.0), 1.0, &bubble)));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &lens)));
+    world.add(Box::new(Sphere::new(&Point3::new(2.4, 1.0, 0.0), 1.0, &tempered)));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn bump_mapping(image_file : &str, assets : &AssetResolver) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 200;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 4.0, 14.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::zero()
+    );
+    // Low sun so the relief casts shading
+    camera.set_environment(Box::new(PreethamSky::new(15.0, 60.0, 3.0, DAYLIGHT_INTENSITY)));
+
+    // Make materials
+    let mut rng = thread_rng();
+    let tiles = ImageTexture::load(assets, "tiles_normal.png", ColourSpace::Raw).expect("Could not load texture");
+    let stone_colour = SolidColour::new(&Colour::new(0.6, 0.55, 0.5));
+    let stone = Lambertian::new(&stone_colour);
+    let tiled_stone = BumpMapped::normal_map(&stone, &tiles);
+    let marble = MarbleTexture::new(&mut rng, 4.0);
+    let gold = Conductor::gold(0.15);
+    let hammered_gold = BumpMapped::height(&gold, &marble, 0.05);
+    let glass = Dielectric::new(1.5);
+    let rippled_glass = BumpMapped::height(&glass, &marble, 0.01);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-8.0, 0.0, 8.0), &Vec3::new(16.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, -16.0), &tiled_stone
+    )));
+    let block = make_box(&Point3::new(-1.0, 0.0, -1.0), &Point3::new(1.0, 2.0, 1.0), &tiled_stone);
+    world.add(Box::new(Translated::new(&Vec3::new(-3.0, 0.0, 0.0), Box::new(RotateY::new(30.0, Box::new(block))))));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &hammered_gold)));
+    world.add(Box::new(Sphere::new(&Point3::new(3.0, 1.0, 0.0), 1.0, &rippled_glass)));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn cutout(image_file : &str, assets : &AssetResolver) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 200;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 2.0, 12.0),
+        &Point3::new(0.0, 1.2, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::zero()
+    );
+    camera.set_environment(Box::new(PreethamSky::new(40.0, 120.0, 3.0, DAYLIGHT_INTENSITY)));
+
+    // Make materials
+    let ground_colour = SolidColour::new(&Colour::new(0.5, 0.45, 0.4));
+    let ground_material = Lambertian::new(&ground_colour);
+    let ball_colour = SolidColour::new(&Colour::new(0.8, 0.1, 0.1));
+    let ball_material = Lambertian::new(&ball_colour);
+    let lattice = ImageTexture::load(assets, "lattice.png", ColourSpace::Srgb).expect("Could not load texture");
+    let wire_colour = SolidColour::new(&Colour::new(0.7, 0.7, 0.7));
+    let wire = Metal::new(&wire_colour, 0.3);
+    let fence = Cutout::new(&wire, &lattice, AlphaMask::Threshold(0.5));
+    let leaf_texture = ImageTexture::load(assets, "leaf.png", ColourSpace::Srgb).expect("Could not load texture");
+    let leaf_surface = Lambertian::new(&leaf_texture);
+    // Hard edged leaves on the left and soft edges blended by alpha on the right
+    let sharp_leaf = Cutout::new(&leaf_surface, &leaf_texture, AlphaMask::Threshold(0.5));
+    let soft_leaf = Cutout::new(&leaf_surface, &leaf_texture, AlphaMask::Stochastic);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &ground_material)));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, 1.0, -1.5), 1.0, &ball_material)));
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-2.0, 0.0, 1.0), &Vec3::new(4.0, 0.0, 0.0), &Vec3::new(0.0, 2.5, 0.0), &fence
+    )));
+    for (side, leaf) in [(-1.0, &sharp_leaf), (1.0, &soft_leaf)] {
+        for i in 0..5 {
+            let angle = i as f64 * 0.7;
+            let corner = Point3::new(side * (3.2 + 0.4 * angle.sin()), 0.3 + 0.45 * i as f64, 0.5 * angle.cos());
+            let along = Vec3::new(0.9 * angle.cos(), 0.2, 0.9 * angle.sin());
+            world.add(Box::new(Quadrilateral::new(&corner, &along, &Vec3::new(-0.3 * angle.sin(), 1.2, 0.3), leaf)));
+        }
+    }
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn emitters(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 500;
+    let max_depth : u8 = 50;
+    let fov : f64 = 40.0;
+    let camera = Camera::new(
+        &Point3::new(0.0, 3.0, 12.0),
+        &Point3::new(0.0, 1.5, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::zero()
+    );
+
+    // Make materials
+    let grey = SolidColour::new(&Colour::new(0.7, 0.7, 0.7));
+    let grey_material = Lambertian::new(&grey);
+    // Warm ceiling panel facing down given by its power
+    let warm = SolidColour::new(&blackbody(2700.0));
+    let mut warm_panel = DiffuseLight::new(&warm);
+    warm_panel.set_two_sided(false);
+    warm_panel.set_power(150.0, 4.0);
+    // Daylight panel standing in the room lighting both ways
+    let daylight = SolidColour::new(&blackbody(6500.0));
+    let mut daylight_panel = DiffuseLight::new(&daylight);
+    daylight_panel.set_power(150.0, 3.0);
+    // Narrow downlight given in lumens with its angular falloff
+    let halogen = SolidColour::new(&blackbody(3200.0));
+    let mut downlight = DiffuseLight::new(&halogen);
+    downlight.set_two_sided(false);
+    downlight.set_profile(&[(0.0, 1000.0), (15.0, 950.0), (25.0, 600.0), (35.0, 150.0), (45.0, 0.0)]);
+    downlight.set_lumens(10000.0, 0.25);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), 1000.0, &grey_material)));
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-10.0, 0.0, -4.0), &Vec3::new(20.0, 0.0, 0.0), &Vec3::new(0.0, 8.0, 0.0), &grey_material
+    )));
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-5.0, 5.0, -1.0), &Vec3::new(2.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, 2.0), &warm_panel
+    )));
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(0.0, 0.0, -1.0), &Vec3::new(0.0, 0.0, 1.0), &Vec3::new(0.0, 3.0, 0.0), &daylight_panel
+    )));
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(4.25, 5.0, -0.25), &Vec3::new(0.5, 0.0, 0.0), &Vec3::new(0.0, 0.0, 0.5), &downlight
+    )));
+    for x in [-4.0, -1.5, 1.5, 4.5] {
+        world.add(Box::new(Sphere::new(&Point3::new(x, 0.6, 0.5), 0.6, &grey_material)));
+    }
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn texture_filtering(image_file : &str, assets : &AssetResolver) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 100;
+    let max_depth : u8 = 50;
+    let fov : f64 = 40.0;
+    let camera = Camera::new(
+        &Point3::new(0.0, 1.5, 6.0),
+        &Point3::new(0.0, 0.5, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::new(0.7, 0.8, 1.0)
+    );
+
+    // Make materials
+    // Strips tiling the map many times into the distance, each filtered differently
+    let mut nearest = ImageTexture::load(assets, "earthmap.jpg", ColourSpace::Srgb).expect("Could not load texture");
+    nearest.set_wrap(Wrap::Repeat);
+    nearest.set_uv_transform(2.0, 40.0, 0.0, 0.0);
+    let mut trilinear = ImageTexture::load(assets, "earthmap.jpg", ColourSpace::Srgb).expect("Could not load texture");
+    trilinear.set_filter(Filter::Bilinear, MipFilter::Trilinear);
+    trilinear.set_wrap(Wrap::Mirror);
+    trilinear.set_uv_transform(2.0, 40.0, 0.0, 0.0);
+    let mut ewa = ImageTexture::load(assets, "earthmap.jpg", ColourSpace::Srgb).expect("Could not load texture");
+    ewa.set_filter(Filter::Bicubic, MipFilter::Ewa);
+    ewa.set_wrap(Wrap::Repeat);
+    ewa.set_uv_transform(2.0, 40.0, 0.0, 0.0);
+    // Map shrunk into the middle of a sign with a border round it
+    let mut sign = ImageTexture::load(assets, "earthmap.jpg", ColourSpace::Srgb).expect("Could not load texture");
+    sign.set_filter(Filter::Bilinear, MipFilter::Ewa);
+    sign.set_wrap(Wrap::Border(Colour::new(0.9, 0.9, 0.9)));
+    sign.set_uv_transform(1.5, 1.5, -0.25, -0.25);
+    let nearest_material = Lambertian::new(&nearest);
+    let trilinear_material = Lambertian::new(&trilinear);
+    let ewa_material = Lambertian::new(&ewa);
+    let sign_material = Lambertian::new(&sign);
+
+    // Make world
+    let mut world = HittableList::new();
+    for (x, material) in [(-3.0, &nearest_material), (-1.0, &trilinear_material), (1.0, &ewa_material)] {
+        world.add(Box::new(Quadrilateral::new(
+            &Point3::new(x, 0.0, 4.0), &Vec3::new(2.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, -200.0), material
+        )));
+    }
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-0.8, 1.2, -2.0), &Vec3::new(1.6, 0.0, 0.0), &Vec3::new(0.0, 0.8, 0.0), &sign_material
+    )));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn colour_management(image_file : &str, assets : &AssetResolver) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 100;
+    let max_depth : u8 = 50;
+    let fov : f64 = 25.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 0.0, 16.0),
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::new(0.7, 0.8, 1.0)
+    );
+    camera.set_output_space(ColourSpace::Srgb);
+
+    // Make materials
+    // Same map read as linear which washes it out, as a plain gamma and as sRGB
+    let mut textures = Vec::new();
+    for space in [ColourSpace::Linear, ColourSpace::Gamma(2.2), ColourSpace::Srgb] {
+        let mut texture = ImageTexture::load(assets, "earthmap.jpg", space).expect("Could not load texture");
+        texture.set_filter(Filter::Bilinear, MipFilter::Trilinear);
+        textures.push(texture);
+    }
+    let materials : Vec<Lambertian> = textures.iter().map(|texture| Lambertian::new(texture)).collect();
+
+    // Make world
+    let mut world = HittableList::new();
+    for (x, material) in [-4.4, 0.0, 4.4].iter().zip(materials.iter()) {
+        world.add(Box::new(Sphere::new(&Point3::new(*x, 0.0, 0.0), 2.0, material)));
+    }
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn hdr_textures(image_file : &str, assets : &AssetResolver) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 400;
+    let max_depth : u8 = 50;
+    let fov : f64 = 35.0;
+    let camera = Camera::new(
+        &Point3::new(0.0, 2.5, 9.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::zero()
+    );
+
+    // Make materials
+    // Neon sign with values far above one lights the scene from its texture
+    let mut neon = ImageTexture::load(assets, "neon.hdr", ColourSpace::Linear).expect("Could not load texture");
+    neon.set_filter(Filter::Bilinear, MipFilter::Trilinear);
+    let sign = DiffuseLight::new(&neon);
+    // Floor roughness rises smoothly from mirror to matt along a 16-bit ramp
+    let ramp = ImageTexture::load(assets, "ramp16.png", ColourSpace::Raw).expect("Could not load texture");
+    let floor_colour = SolidColour::new(&Colour::new(0.3, 0.3, 0.3));
+    let floor = Principled::new(&floor_colour, Scalar::Value(1.0), Scalar::Texture(&ramp));
+    let white = SolidColour::new(&Colour::new(0.8, 0.8, 0.8));
+    let white_material = Lambertian::new(&white);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-5.0, 0.0, 4.0), &Vec3::new(10.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, -8.0), &floor
+    )));
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-3.0, 1.0, -3.0), &Vec3::new(6.0, 0.0, 0.0), &Vec3::new(0.0, 1.5, 0.0), &sign
+    )));
+    for x in [-2.0, 0.0, 2.0] {
+        world.add(Box::new(Sphere::new(&Point3::new(x, 0.5, 0.0), 0.5, &white_material)));
+    }
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn procedural_noise(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 100;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let camera = Camera::new(
+        &Point3::new(0.0, 0.0, 14.0),
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::new(0.7, 0.8, 1.0)
+    );
+
+    // Make materials
+    let mut rng = thread_rng();
+    // Cellular noise on top, fractal noise below
+    let cells = CellularTexture::new(&mut rng, CellFeature::F1, 3.0);
+    let bulges = CellularTexture::new(&mut rng, CellFeature::F2, 3.0);
+    let cracks = CellularTexture::new(&mut rng, CellFeature::F2MinusF1, 3.0);
+    let clouds = FractalTexture::new(&mut rng, Fractal::Fbm, 2.0);
+    let mut mountains = FractalTexture::new(&mut rng, Fractal::Ridged, 1.5);
+    mountains.set_octaves(8, 2.1, 0.6);
+    let marble = MarbleTexture::new(&mut rng, 4.0);
+    let folded = WarpedTexture::new(&mut rng, &marble, 1.0, 1.5);
+    let textures : [&dyn Texture; 6] = [&cells, &bulges, &cracks, &clouds, &mountains, &folded];
+    let materials : Vec<Lambertian> = textures.iter().map(|texture| Lambertian::new(*texture)).collect();
+
+    // Make world
+    let mut world = HittableList::new();
+    for (i, material) in materials.iter().enumerate() {
+        let x = 2.4 * (i % 3) as f64 - 2.4;
+        let y = if i < 3 { 1.2 } else { -1.2 };
+        world.add(Box::new(Sphere::new(&Point3::new(x, y, 0.0), 1.0, material)));
+    }
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn noise_animation(image_file : &str, frame : u32) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 100;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let mut camera = Camera::new(
+        &Point3::new(0.0, 4.0, 14.0),
+        &Point3::new(0.0, 1.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::new(0.7, 0.8, 1.0)
+    );
+    camera.set_frame(frame, 24.0, 180.0);
+
+    // Make materials
+    // Seeded so every frame renders the same noise, only moved on in time
+    let mut clouds = FractalTexture::with_noise(Perlin::seeded(7), Fractal::Fbm, 1.5);
+    clouds.set_speed(4.0);
+    let clouds_material = Lambertian::new(&clouds);
+    let mut lava = FractalTexture::with_noise(Perlin::seeded(11), Fractal::Ridged, 1.0);
+    lava.set_octaves(5, 2.0, 0.6);
+    lava.set_speed(0.5);
+    let lava_material = Lambertian::new(&lava);
+    // Floor noise repeats every 4 lattice cells, 8 units at this scale
+    let mut tiling = Perlin::seeded(3);
+    tiling.set_period(4);
+    let floor = FractalTexture::with_noise(tiling, Fractal::Fbm, 0.5);
+    let floor_material = Lambertian::new(&floor);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-12.0, 0.0, 6.0),
+        &Vec3::new(24.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, -24.0),
+        &floor_material
+    )));
+    world.add(Box::new(Sphere::new(&Point3::new(-1.8, 1.5, 0.0), 1.5, &clouds_material)));
+    world.add(Box::new(Sphere::new(&Point3::new(1.8, 1.5, 0.0), 1.5, &lava_material)));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn patterns(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 100;
+    let max_depth : u8 = 50;
+    let fov : f64 = 35.0;
+    let camera = Camera::new(
+        &Point3::new(0.0, 3.0, 10.0),
+        &Point3::new(0.0, 1.2, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::new(0.7, 0.8, 1.0)
+    );
+
+    // Make materials
+    let mut rng = thread_rng();
+    // Parquet floor of light and dark wood squares
+    let pale = SolidColour::new(&Colour::new(0.75, 0.55, 0.32));
+    let grain = SolidColour::new(&Colour::new(0.45, 0.27, 0.12));
+    let walnut = SolidColour::new(&Colour::new(0.3, 0.17, 0.08));
+    let light_wood = WoodTexture::new(&mut rng, 0.15, &pale, &grain);
+    let mut dark_wood = WoodTexture::new(&mut rng, 0.1, &grain, &walnut);
+    dark_wood.set_grain(1.0);
+    let parquet = UvChecker::new(8.0, 8.0, &light_wood, &dark_wood);
+    let parquet_material = Lambertian::new(&parquet);
+    // Brick wall with noisy bricks
+    let clay = SolidColour::new(&Colour::new(0.55, 0.18, 0.1));
+    let soot = SolidColour::new(&Colour::new(0.3, 0.12, 0.08));
+    let shading = FractalTexture::new(&mut rng, Fractal::Fbm, 3.0);
+    let bricks = Checker::new(0.6, &clay, &soot);
+    let weathered = WarpedTexture::new(&mut rng, &bricks, 1.0, 0.3);
+    let mortar = SolidColour::new(&Colour::new(0.7, 0.7, 0.65));
+    let wall = BrickTexture::new(1.0 / 12.0, 1.0 / 24.0, 0.004, &weathered, &mortar);
+    let wall_material = Lambertian::new(&wall);
+    let mut tiles = BrickTexture::new(0.125, 0.25, 0.01, &shading, &mortar);
+    tiles.set_row_offset(0.0);
+    let tiles_material = Lambertian::new(&tiles);
+    // Spheres showing the texture coordinate patterns and gradients
+    let test_pattern = UvTestPattern::new(8.0);
+    let test_material = Lambertian::new(&test_pattern);
+    let black = SolidColour::new(&Colour::new(0.05, 0.05, 0.05));
+    let white = SolidColour::new(&Colour::new(0.9, 0.9, 0.9));
+    let grid = UvGrid::new(16.0, 8.0, 0.1, &black, &white);
+    let grid_material = Lambertian::new(&grid);
+    let sunset = SolidColour::new(&Colour::new(0.9, 0.4, 0.1));
+    let sky = SolidColour::new(&Colour::new(0.1, 0.2, 0.7));
+    let linear = GradientTexture::linear(&Point3::new(-1.8, 0.2, 0.0), &Point3::new(-1.8, 1.8, 0.0), &sunset, &sky);
+    let linear_material = Lambertian::new(&linear);
+    let radial = GradientTexture::radial(&Point3::new(1.8, 1.0, 0.8), 1.0, &white, &light_wood);
+    let radial_material = Lambertian::new(&radial);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-8.0, 0.0, 6.0),
+        &Vec3::new(16.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, -16.0),
+        &parquet_material
+    )));
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-8.0, 0.0, -2.0),
+        &Vec3::new(16.0, 0.0, 0.0),
+        &Vec3::new(0.0, 8.0, 0.0),
+        &wall_material
+    )));
+    world.add(Box::new(make_box(&Point3::new(-5.0, 0.0, -1.0), &Point3::new(-3.5, 1.5, 0.5), &tiles_material)));
+    world.add(Box::new(Sphere::new(&Point3::new(-1.8, 1.0, 0.0), 0.8, &linear_material)));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 0.8, &test_material)));
+    world.add(Box::new(Sphere::new(&Point3::new(1.8, 1.0, 0.0), 0.8, &radial_material)));
+    world.add(Box::new(Sphere::new(&Point3::new(4.0, 1.0, 0.0), 0.8, &grid_material)));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn texture_nodes(image_file : &str) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 100;
+    let max_depth : u8 = 50;
+    let fov : f64 = 30.0;
+    let camera = Camera::new(
+        &Point3::new(0.0, 0.0, 16.0),
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::new(0.7, 0.8, 1.0)
+    );
+
+    // Make materials
+    let mut rng = thread_rng();
+    let moss = SolidColour::new(&Colour::new(0.2, 0.4, 0.1));
+    let rock = SolidColour::new(&Colour::new(0.5, 0.45, 0.4));
+    let white = SolidColour::new(&Colour::new(0.9, 0.9, 0.9));
+    let black = SolidColour::new(&Colour::new(0.05, 0.05, 0.05));
+    // Moss growing over rock where the noise is high
+    let noise = FractalTexture::new(&mut rng, Fractal::Fbm, 2.0);
+    let coverage = RampTexture::new(&noise, &[(0.47, Colour::zero()), (0.53, Colour::new(1.0, 1.0, 1.0))]);
+    let mossy = MixTexture::new(Blend::Mix, Scalar::Texture(&coverage), &rock, &moss);
+    // Cracks mapped through a ramp to glowing lava
+    let cracks = CellularTexture::new(&mut rng, CellFeature::F2MinusF1, 2.5);
+    let lava = RampTexture::new(&cracks, &[
+        (0.0, Colour::new(1.0, 0.9, 0.2)),
+        (0.05, Colour::new(0.9, 0.2, 0.0)),
+        (0.2, Colour::new(0.1, 0.05, 0.05))
+    ]);
+    // Test pattern rotated in hue and faded
+    let test_pattern = UvTestPattern::new(8.0);
+    let shifted = HsvTexture::new(&test_pattern, 120.0, 0.6, 0.9);
+    // Dark veined marble, the turbulence pushes it past one so clamp before inverting
+    let marble = MarbleTexture::new(&mut rng, 4.0);
+    let clamped = ClampTexture::new(&marble, 0.0, 1.0);
+    let dark_marble = InvertTexture::new(&clamped);
+    // Grid lines multiplied over a checker and glowing rings added on top
+    let checker = UvChecker::new(8.0, 4.0, &moss, &white);
+    let grid = UvGrid::new(16.0, 8.0, 0.1, &black, &white);
+    let gridded = MixTexture::new(Blend::Multiply, Scalar::Value(1.0), &checker, &grid);
+    let rings = GradientTexture::radial(&Point3::new(-1.2, -1.2, 1.0), 1.0, &black, &moss);
+    let glowing = MixTexture::new(Blend::Add, Scalar::Value(0.8), &gridded, &rings);
+    // Checker turned and stretched in texture coordinates and in space
+    let turned = TransformTexture::uv(&checker, (2.0, 1.0), 45.0, (0.0, 0.0));
+    let solid = Checker::new(0.3, &rock, &white);
+    let tilted = TransformTexture::object(
+        &solid,
+        &Vec3::new(1.0, 3.0, 1.0),
+        &Quaternion::from_axis_angle(&Vec3::new(1.0, 0.0, 1.0), 30.0),
+        &Vec3::new(0.15, 0.0, 0.0)
+    );
+    let textures : [&dyn Texture; 8] = [&mossy, &lava, &shifted, &dark_marble, &gridded, &glowing, &turned, &tilted];
+    let materials : Vec<Lambertian> = textures.iter().map(|texture| Lambertian::new(*texture)).collect();
+
+    // Make world
+    let mut world = HittableList::new();
+    for (i, material) in materials.iter().enumerate() {
+        let x = 2.4 * (i % 4) as f64 - 3.6;
+        let y = if i < 4 { 1.2 } else { -1.2 };
+        world.add(Box::new(Sphere::new(&Point3::new(x, y, 0.0), 1.0, material)));
+    }
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn projections(image_file : &str, assets : &AssetResolver) {
+    // Camera
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width : u32 = 400;
+    let samples_per_pixel = 100;
+    let max_depth : u8 = 50;
+    let fov : f64 = 35.0;
+    let camera = Camera::new(
+        &Point3::new(0.0, 4.0, 10.0),
+        &Point3::new(0.0, 0.8, 0.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        fov,
+        10.0,
+        0.0,
+        Colour::new(0.7, 0.8, 1.0)
+    );
+
+    // Make materials
+    let ground = SolidColour::new(&Colour::new(0.5, 0.5, 0.5));
+    let ground_material = Lambertian::new(&ground);
+    // Bricks the same size on every face of a box instead of stretched across each quad
+    let clay = SolidColour::new(&Colour::new(0.55, 0.18, 0.1));
+    let mortar = SolidColour::new(&Colour::new(0.7, 0.7, 0.65));
+    let bricks = BrickTexture::new(0.4, 0.2, 0.03, &clay, &mortar);
+    let triplanar = ProjectedTexture::triplanar(&bricks, 4.0);
+    let triplanar_material = Lambertian::new(&triplanar);
+    // The globe is turned and moved after it is made, the map turns with it and still filters through the projection
+    let mut earth = ImageTexture::load(assets, "earthmap.jpg", ColourSpace::Srgb).expect("Could not load texture");
+    earth.set_filter(Filter::Bilinear, MipFilter::Trilinear);
+    earth.set_wrap(Wrap::Repeat);
+    let globe = ProjectedTexture::spherical(&earth, Axis3::Y);
+    let globe_material = Lambertian::new(&globe);
+    // Label wrapped around a box and a grid shone straight down onto a sphere
+    let test_pattern = UvTestPattern::new(8.0);
+    let label = ProjectedTexture::cylindrical(&test_pattern, Axis3::Y);
+    let label_material = Lambertian::new(&label);
+    let black = SolidColour::new(&Colour::new(0.05, 0.05, 0.05));
+    let white = SolidColour::new(&Colour::new(0.9, 0.9, 0.9));
+    let grid = UvGrid::new(4.0, 4.0, 0.08, &black, &white);
+    let mut decal = ProjectedTexture::planar(&grid, Axis3::Y);
+    decal.set_centre(&Point3::new(0.5, 0.0, 0.5));
+    let decal_material = Lambertian::new(&decal);
+
+    // Make world
+    let mut world = HittableList::new();
+    world.add(Box::new(Quadrilateral::new(
+        &Point3::new(-10.0, 0.0, 10.0),
+        &Vec3::new(20.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, -20.0),
+        &ground_material
+    )));
+    let brick_box = Box::new(make_box(&Point3::new(-0.8, 0.0, -0.8), &Point3::new(0.8, 1.6, 0.8), &triplanar_material));
+    world.add(Box::new(Translated::new(&Vec3::new(-3.6, 0.0, 0.0), Box::new(RotateY::new(30.0, brick_box)))));
+    let ball = Box::new(Sphere::new(&Point3::zero(), 1.0, &globe_material));
+    world.add(Box::new(Translated::new(&Vec3::new(-1.0, 1.0, 0.0), Box::new(RotateY::new(120.0, ball)))));
+    let tin = Box::new(make_box(&Point3::new(-0.7, 0.0, -0.7), &Point3::new(0.7, 1.6, 0.7), &label_material));
+    world.add(Box::new(Translated::new(&Vec3::new(1.4, 0.0, 0.0), Box::new(RotateY::new(-20.0, tin)))));
+    let dome = Box::new(Sphere::new(&Point3::zero(), 0.9, &decal_material));
+    world.add(Box::new(Translated::new(&Vec3::new(3.6, 0.9, 0.0), dome)));
+
+    // Render
+    let world_ordered = BVH::new(world);
+    camera.render(image_file, &world_ordered);
+}
+
+fn main() {
+    // Asset directories can be given with --assets, the rest are the scene and frame
+    let mut positional = Vec::new();
+    let mut asset_paths = Vec::new();
+    let mut arguments = args().skip(1);
+    while let Some(arg) = arguments.next() {
+        if arg == "--assets" {
+            asset_paths.extend(arguments.next().map(PathBuf::from));
+        } else {
+            positional.push(arg);
+        }
+    }
+    let assets = AssetResolver::standard(&asset_paths);
+    let scene = positional.first().cloned().unwrap_or("many_spheres_dark".to_string());
     let filename = "./renders/".to_string() + &scene + ".png";
+    let frame = positional.get(1).and_then(|arg| arg.parse::<u32>().ok()).unwrap_or(0);
     match scene.as_str() {
         "many_spheres" => many_spheres_scene(&filename, false),
         "bouncing_spheres" => bouncing_spheres(&filename),
         "checkered_spheres" => checkered_spheres(&filename),
-        "earth" => earth(&filename),
+        "earth" => earth(&filename, &assets),
         "perlin_spheres" => perlin_spheres(&filename),
         "quads" => quads(&filename),
         "simple_light" => simple_light(&filename),
         "cornell_box" => cornell_box(&filename),
         "cornell_smoke" => cornell_smoke(&filename),
-        "final_scene_fast" => final_scene(&filename, 400, 250, 4),
-        "final_scene" => final_scene(&filename, 800, 10000, 40),
+        "final_scene_fast" => final_scene(&filename, &assets, 400, 250, 4),
+        "final_scene" => final_scene(&filename, &assets, 800, 10000, 40),
         "many_spheres_dark" => many_spheres_scene(&filename, true),
+        "stage_lights" => stage_lights(&filename),
+        "environment_lit" => environment_lit(&filename, &assets),
+        "metals" => metals(&filename),
+        "frosted_glass" => frosted_glass(&filename),
+        "coloured_glass" => coloured_glass(&filename),
+        "dispersion" => dispersion(&filename),
+        "principled" => principled(&filename),
+        "layered" => layered(&filename),
+        "rough_diffuse" => rough_diffuse(&filename),
+        "subsurface" => subsurface(&filename),
+        "thin_film" => thin_film(&filename),
+        "bump_mapping" => bump_mapping(&filename, &assets),
+        "cutout" => cutout(&filename, &assets),
+        "emitters" => emitters(&filename),
+        "texture_filtering" => texture_filtering(&filename, &assets),
+        "colour_management" => colour_management(&filename, &assets),
+        "hdr_textures" => hdr_textures(&filename, &assets),
+        "procedural_noise" => procedural_noise(&filename),
+        "noise_animation" => noise_animation(&format!("./renders/noise_animation_{:03}.png", frame), frame),
+        "patterns" => patterns(&filename),
+        "texture_nodes" => texture_nodes(&filename),
+        "projections" => projections(&filename, &assets),
+        "animated" => animated(&format!("./renders/animated_{:03}.png", frame), frame),
         _ => println!("Please enter valid scene name")
     }
 }
\ No newline at end of file
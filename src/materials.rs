@@ -1,17 +1,44 @@
+use std::f64::consts::PI;
 use rand::Rng;
 use rand::rngs::ThreadRng;
-use crate::colour::Colour;
-use crate::hittables::HitRecord;
+use crate::colour::{attenuate, Colour};
+use crate::hittables::{HitRecord, Hittable};
+use crate::interval::Interval;
 use crate::ray::Ray;
-use crate::textures::Texture;
-use crate::vec3::{dot, random_unit, reflect, refract, Point3};
+use crate::spectrum::{rgb_to_spectrum, LAMBDA_REFERENCE, RGB_WAVELENGTHS};
+use crate::textures::{Scalar, Texture};
+use crate::microfacet::{evaluate_dielectric, fresnel_conductor_colour, fresnel_dielectric, fresnel_thin_film, sample_dielectric, Ggx};
+use crate::vec3::{dot, random_unit, reflect, refract, OrthonormalBasis, Vec3};
 
 pub trait Material : Sync {
     fn scatter(&self, rng : &mut ThreadRng, ray_in : &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)>;
 
-    fn emitted(&self, _u : f64, _v : f64, _point : &Point3) -> Colour {
+    // Light leaving the surface back along the ray
+    fn emitted(&self, _ray_in : &Ray, _hit_record : &HitRecord) -> Colour {
         Colour::zero()
     }
+
+    // Scattering towards direction times the cosine and the pdf of scatter choosing it,
+    // None if scatter only picks discrete directions so lights cannot be sampled
+    fn evaluate(&self, _ray_in : &Ray, _hit_record : &HitRecord, _direction : &Vec3) -> Option<(Colour, f64)> {
+        None
+    }
+
+    // Scattering depends on the wavelength of the ray so only the hero wavelength survives
+    fn is_dispersive(&self) -> bool {
+        false
+    }
+
+    // Materials carrying light under the surface give the weight and the hit where it leaves,
+    // the path is then shaded there so lights can be sampled at the exit
+    fn subsurface<'a>(&'a self, _rng : &mut ThreadRng, _ray_in : &Ray, _hit_record : &HitRecord) -> Option<(Colour, HitRecord<'a>)> {
+        None
+    }
+
+    // Whether the surface is missing at the hit so rays carry on through it
+    fn cutout(&self, _rng : &mut ThreadRng, _hit_record : &HitRecord) -> bool {
+        false
+    }
 }
 
 pub struct Lambertian<'tex> {
@@ -31,7 +58,138 @@ impl<'tex> Material for Lambertian<'tex> {
         if scatter_direction.near_zero() {
             scatter_direction = hit_record.normal;
         }
-        Some( (self.albedo.value(hit_record.u, hit_record.v, &hit_record.point), Ray::new(&hit_record.point, &scatter_direction, ray_in.time)) )
+        Some( (hit_record.texture_value(self.albedo), Ray::new(&hit_record.point, &scatter_direction, ray_in.time)) )
+    }
+
+    fn evaluate(&self, _ray_in : &Ray, hit_record : &HitRecord, direction : &Vec3) -> Option<(Colour, f64)> {
+        let cos_th = dot(&hit_record.normal, &direction.unit());
+        if cos_th <= 0.0 {
+            return Some((Colour::zero(), 0.0));
+        }
+        let albedo = hit_record.texture_value(self.albedo);
+        Some((albedo * (cos_th / PI), cos_th / PI))
+    }
+}
+
+// Cosine weighted direction about the normal, shared by the diffuse models
+fn cosine_direction(rng : &mut ThreadRng, normal : &Vec3) -> Vec3 {
+    let direction = normal + random_unit(rng);
+    if direction.near_zero() {
+        *normal
+    } else {
+        direction.unit()
+    }
+}
+
+// Rough diffuse made of tiny lambertian V grooves with slopes of standard deviation sigma
+pub struct OrenNayar<'tex> {
+    albedo : &'tex dyn Texture,
+    a : f64,
+    b : f64
+}
+
+impl<'tex> OrenNayar<'tex> {
+    // Sigma in degrees, zero is the same as lambertian
+    pub fn new(albedo : &'tex dyn Texture, sigma : f64) -> OrenNayar<'tex> {
+        let sigma2 = (sigma * PI / 180.0).powi(2);
+        OrenNayar {
+            albedo,
+            a : 1.0 - sigma2 / (2.0 * (sigma2 + 0.33)),
+            b : 0.45 * sigma2 / (sigma2 + 0.09)
+        }
+    }
+
+    // Scattering times pi for both directions above the surface
+    fn reflectance(&self, normal : &Vec3, wo : &Vec3, wi : &Vec3) -> f64 {
+        let cos_o = dot(normal, wo);
+        let cos_i = dot(normal, wi);
+        let tangent_o = wo - cos_o * normal;
+        let tangent_i = wi - cos_i * normal;
+        let lengths = (tangent_o.length_squared() * tangent_i.length_squared()).sqrt();
+        let cos_phi = if lengths > 0.0 { dot(&tangent_o, &tangent_i) / lengths } else { 0.0 };
+        // Alpha is the larger angle from the normal, beta the smaller
+        let cos_alpha = cos_o.min(cos_i);
+        let cos_beta = cos_o.max(cos_i);
+        let sin_alpha = (1.0 - cos_alpha * cos_alpha).max(0.0).sqrt();
+        let tan_beta = (1.0 - cos_beta * cos_beta).max(0.0).sqrt() / cos_beta;
+        self.a + self.b * cos_phi.max(0.0) * sin_alpha * tan_beta
+    }
+}
+
+impl<'tex> Material for OrenNayar<'tex> {
+    fn scatter(&self, rng: &mut ThreadRng, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
+        let wo = -ray_in.direction.unit();
+        let wi = cosine_direction(rng, &hit_record.normal);
+        if dot(&hit_record.normal, &wi) <= 0.0 {
+            return None;
+        }
+        let albedo = hit_record.texture_value(self.albedo);
+        let reflectance = self.reflectance(&hit_record.normal, &wo, &wi);
+        Some((reflectance * albedo, Ray::new(&hit_record.point, &wi, ray_in.time)))
+    }
+
+    fn evaluate(&self, ray_in : &Ray, hit_record : &HitRecord, direction : &Vec3) -> Option<(Colour, f64)> {
+        let wo = -ray_in.direction.unit();
+        let wi = direction.unit();
+        let cos_th = dot(&hit_record.normal, &wi);
+        if cos_th <= 0.0 {
+            return Some((Colour::zero(), 0.0));
+        }
+        let albedo = hit_record.texture_value(self.albedo);
+        let reflectance = self.reflectance(&hit_record.normal, &wo, &wi);
+        Some((albedo * (reflectance * cos_th / PI), cos_th / PI))
+    }
+}
+
+// Diffuse base with a retro and grazing sheen from fibres like velvet, using the
+// Charlie distribution (Estevez and Kulla 2017)
+pub struct Velvet<'tex> {
+    albedo : &'tex dyn Texture,
+    sheen : &'tex dyn Texture,
+    inv_roughness : f64
+}
+
+impl<'tex> Velvet<'tex> {
+    pub fn new(albedo : &'tex dyn Texture, sheen : &'tex dyn Texture, roughness : f64) -> Velvet<'tex> {
+        Velvet { albedo, sheen, inv_roughness : 1.0 / roughness.clamp(0.07, 1.0) }
+    }
+
+    // Scattering for both directions above the surface
+    fn scattering(&self, hit_record : &HitRecord, wo : &Vec3, wi : &Vec3) -> Colour {
+        let half = wo + wi;
+        let diffuse = hit_record.texture_value(self.albedo) / PI;
+        if half.length_squared() <= 0.0 {
+            return diffuse;
+        }
+        let cos_h = dot(&hit_record.normal, &half.unit());
+        let sin_h = (1.0 - cos_h * cos_h).max(0.0).sqrt();
+        let distribution = (2.0 + self.inv_roughness) * sin_h.powf(self.inv_roughness) / (2.0 * PI);
+        let cos_o = dot(&hit_record.normal, wo).max(0.0);
+        let cos_i = dot(&hit_record.normal, wi).max(0.0);
+        let visibility = 1.0 / (4.0 * (cos_i + cos_o - cos_i * cos_o));
+        diffuse + (distribution * visibility) * hit_record.texture_value(self.sheen)
+    }
+}
+
+impl<'tex> Material for Velvet<'tex> {
+    fn scatter(&self, rng: &mut ThreadRng, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
+        let wo = -ray_in.direction.unit();
+        let wi = cosine_direction(rng, &hit_record.normal);
+        if dot(&hit_record.normal, &wi) <= 0.0 {
+            return None;
+        }
+        let colour = PI * self.scattering(hit_record, &wo, &wi);
+        Some((colour, Ray::new(&hit_record.point, &wi, ray_in.time)))
+    }
+
+    fn evaluate(&self, ray_in : &Ray, hit_record : &HitRecord, direction : &Vec3) -> Option<(Colour, f64)> {
+        let wo = -ray_in.direction.unit();
+        let wi = direction.unit();
+        let cos_th = dot(&hit_record.normal, &wi);
+        if cos_th <= 0.0 {
+            return Some((Colour::zero(), 0.0));
+        }
+        Some((cos_th * self.scattering(hit_record, &wo, &wi), cos_th / PI))
     }
 }
 
@@ -56,18 +214,130 @@ impl<'tex> Material for Metal<'tex> {
     {
         let scatter_direction = reflect(&ray_in.direction, &hit_record.normal);
         let fuzzed_scatter_direction = scatter_direction.unit() + self.fuzz * random_unit(rng);
-        let colour = self.albedo.value(hit_record.u,hit_record.v, &hit_record.point);
+        let colour = hit_record.texture_value(self.albedo);
         Some( (colour, Ray::new(&hit_record.point, &fuzzed_scatter_direction, ray_in.time)) )
     }
 }
 
-pub struct Dielectric {
+// Absorption coefficient so white light is this colour after travelling distance inside,
+// distances of zero or less are held at a tiny positive one
+pub fn absorption_for(colour : &Colour, distance : f64) -> Colour {
+    let distance = distance.max(1e-6);
+    let channel = |val : f64| -val.max(1e-6).ln() / distance;
+    Colour::new(channel(colour.x()), channel(colour.y()), channel(colour.z()))
+}
+
+// Beer-Lambert attenuation along the ray just travelled if it was inside the material
+fn transmittance(absorption : &Colour, ray_in : &Ray, hit_record : &HitRecord) -> Colour {
+    if hit_record.front_face {
+        return Colour::new(1.0, 1.0, 1.0);
+    }
+    let distance = hit_record.t * ray_in.direction.length();
+    Colour::new(
+        (-absorption.x() * distance).exp(),
+        (-absorption.y() * distance).exp(),
+        (-absorption.z() * distance).exp()
+    )
+}
+
+// Index of refraction as a function of wavelength
+#[derive(Copy, Clone)]
+pub enum RefractiveIndex {
+    Constant(f64),
+    // n = a + b / lambda^2 with lambda in micrometres
+    Cauchy(f64, f64),
+    // n^2 = 1 + sum b lambda^2 / (lambda^2 - c) with lambda in micrometres
+    Sellmeier([f64; 3], [f64; 3])
+}
+
+impl RefractiveIndex {
+    pub fn crown_glass() -> RefractiveIndex {
+        RefractiveIndex::Sellmeier([1.03961212, 0.231792344, 1.01046945], [0.00600069867, 0.0200179144, 103.560653])
+    }
+
+    pub fn flint_glass() -> RefractiveIndex {
+        RefractiveIndex::Sellmeier([1.73759695, 0.313747346, 1.89878101], [0.013188707, 0.0623068142, 155.23629])
+    }
+
+    pub fn diamond() -> RefractiveIndex {
+        RefractiveIndex::Cauchy(2.38, 0.0122)
+    }
+
+    // Wavelength in nanometres, without one the reference wavelength is used
+    pub fn at(&self, wavelength : Option<f64>) -> f64 {
+        let lambda = wavelength.unwrap_or(LAMBDA_REFERENCE) / 1000.0;
+        let lambda2 = lambda * lambda;
+        match self {
+            RefractiveIndex::Constant(n) => *n,
+            RefractiveIndex::Cauchy(a, b) => a + b / lambda2,
+            RefractiveIndex::Sellmeier(b, c) => {
+                let sum : f64 = b.iter().zip(c.iter()).map(|(b, c)| b * lambda2 / (lambda2 - c)).sum();
+                (1.0 + sum).sqrt()
+            }
+        }
+    }
+}
+
+// Thin layer such as soap or oil over a surface whose reflections interfere, thickness in nanometres
+pub struct ThinFilm<'tex> {
+    thickness : Scalar<'tex>,
+    min_thickness : f64,
+    max_thickness : f64,
     refraction_index : f64
 }
 
-impl Dielectric {
-    pub fn new(refraction_index : f64) -> Dielectric {
-        Dielectric { refraction_index }
+impl<'tex> ThinFilm<'tex> {
+    pub fn new(thickness : f64, refraction_index : f64) -> ThinFilm<'tex> {
+        ThinFilm { thickness : Scalar::Value(0.0), min_thickness : thickness, max_thickness : thickness, refraction_index }
+    }
+
+    // Thickness goes from min to max as the texture goes from black to white
+    pub fn textured(thickness : &'tex dyn Texture, min_thickness : f64, max_thickness : f64, refraction_index : f64) -> ThinFilm<'tex> {
+        ThinFilm { thickness : Scalar::Texture(thickness), min_thickness, max_thickness, refraction_index }
+    }
+
+    // Reflectance per channel, or at the hero wavelength when rendering spectrally, over a base
+    // of index eta + ik with light arriving through a medium of index incident
+    fn reflectance(&self, hit_record : &HitRecord, wavelength : Option<f64>, cos_th : f64, incident : f64, eta : &Colour, k : &Colour) -> Colour {
+        let amount = self.thickness.value_at_hit(hit_record).clamp(0.0, 1.0);
+        let thickness = self.min_thickness + amount * (self.max_thickness - self.min_thickness);
+        let at = |lambda : f64, eta : f64, k : f64| {
+            fresnel_thin_film(cos_th, incident, thickness, self.refraction_index, eta, k, lambda)
+        };
+        match wavelength {
+            Some(lambda) => {
+                let reflectance = at(lambda, rgb_to_spectrum(eta, lambda), rgb_to_spectrum(k, lambda));
+                Colour::new(reflectance, reflectance, reflectance)
+            },
+            None => {
+                let [red, green, blue] = RGB_WAVELENGTHS;
+                Colour::new(at(red, eta.x(), k.x()), at(green, eta.y(), k.y()), at(blue, eta.z(), k.z()))
+            }
+        }
+    }
+}
+
+pub struct Dielectric<'tex> {
+    refraction_index : RefractiveIndex,
+    absorption : Colour,
+    film : Option<ThinFilm<'tex>>
+}
+
+impl<'tex> Dielectric<'tex> {
+    pub fn new(refraction_index : f64) -> Dielectric<'tex> {
+        Dielectric::dispersive(RefractiveIndex::Constant(refraction_index), &Colour::zero())
+    }
+
+    pub fn absorbing(refraction_index : f64, absorption : &Colour) -> Dielectric<'tex> {
+        Dielectric::dispersive(RefractiveIndex::Constant(refraction_index), absorption)
+    }
+
+    pub fn dispersive(refraction_index : RefractiveIndex, absorption : &Colour) -> Dielectric<'tex> {
+        Dielectric { refraction_index, absorption : *absorption, film : None }
+    }
+
+    pub fn set_thin_film(&mut self, film : ThinFilm<'tex>) {
+        self.film = Some(film);
     }
 
     fn refractivity_approx(cos_th : f64, ratio : f64) -> f64 {
@@ -77,13 +347,14 @@ impl Dielectric {
     }
 }
 
-impl Material for Dielectric {
+impl<'tex> Material for Dielectric<'tex> {
     fn scatter(&self, rng: &mut ThreadRng, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)>
     {
+        let refraction_index = self.refraction_index.at(ray_in.wavelength);
         let ratio = if hit_record.front_face {
-            1.0 / self.refraction_index
+            1.0 / refraction_index
         } else {
-            self.refraction_index
+            refraction_index
         };
         let in_direction = ray_in.direction.unit();
         let mut cos_th = dot(&-in_direction, &hit_record.normal);
@@ -91,27 +362,124 @@ impl Material for Dielectric {
         let sin_th = (1.0 - cos_th*cos_th).sqrt();
         // Total internal
         let cannot_refract = ratio * sin_th > 1.0;
-        let should_reflect = Self::refractivity_approx(cos_th, ratio) > rng.gen::<f64>();
-        let scatter_direction = if cannot_refract || should_reflect {
-            reflect(&in_direction, &hit_record.normal)
+        let reflectance = match &self.film {
+            Some(film) => {
+                let (incident, base) = if hit_record.front_face { (1.0, refraction_index) } else { (refraction_index, 1.0) };
+                film.reflectance(hit_record, ray_in.wavelength, cos_th, incident, &Colour::new(base, base, base), &Colour::zero())
+            },
+            None => {
+                let reflectance = Self::refractivity_approx(cos_th, ratio);
+                Colour::new(reflectance, reflectance, reflectance)
+            }
+        };
+        // Choose by the average and weight by each channel
+        let probability = (reflectance.x() + reflectance.y() + reflectance.z()) / 3.0;
+        let (weight, scatter_direction) = if cannot_refract {
+            (Colour::new(1.0, 1.0, 1.0), reflect(&in_direction, &hit_record.normal))
+        } else if probability > rng.gen::<f64>() {
+            (reflectance / probability, reflect(&in_direction, &hit_record.normal))
         } else {
-            refract(&in_direction, &hit_record.normal, ratio)
+            let transmitted = Colour::new(1.0, 1.0, 1.0) - reflectance;
+            (transmitted / (1.0 - probability), refract(&in_direction, &hit_record.normal, ratio))
         };
         Some( (
-            Colour::new(1.0,1.0,1.0),
+            attenuate(&weight, &transmittance(&self.absorption, ray_in, hit_record)),
             Ray::new(&hit_record.point, &scatter_direction, ray_in.time)
         ))
     }
+
+    fn is_dispersive(&self) -> bool {
+        !matches!(self.refraction_index, RefractiveIndex::Constant(_)) || self.film.is_some()
+    }
 }
 
+// Luminous efficacy of light at 555nm for giving power in lumens
+const LUMENS_PER_WATT : f64 = 683.0;
+// Steps in the integral of an angular profile over the hemisphere
+const PROFILE_STEPS : usize = 1000;
+
+// Lambertian emitter, by default only from the front face and with the texture as radiance
 #[derive(Clone)]
 pub struct DiffuseLight<'tex> {
-    light : &'tex dyn Texture
+    light : &'tex dyn Texture,
+    two_sided : bool,
+    // Total power and the area it leaves from, the texture then only tints the light
+    power : Option<(f64, f64)>,
+    // Relative intensity against angle from the normal in degrees, uniform when empty
+    profile : Vec<(f64, f64)>,
+    // Integral of the profile times the cosine over the hemisphere
+    profile_integral : f64
 }
 
 impl<'tex> DiffuseLight<'tex> {
     pub fn new(light : &'tex dyn Texture) -> DiffuseLight<'tex> {
-        DiffuseLight { light }
+        DiffuseLight {
+            light,
+            two_sided : true,
+            power : None,
+            profile : Vec::new(),
+            profile_integral : PI
+        }
+    }
+
+    // Lights emit from both sides unless made one-sided, then only from the front face
+    pub fn set_two_sided(&mut self, two_sided : bool) {
+        self.two_sided = two_sided;
+    }
+
+    // Power over all the sides and directions the light emits into, a white texture gives this power
+    pub fn set_power(&mut self, power : f64, area : f64) {
+        self.power = Some((power, area));
+    }
+
+    pub fn set_lumens(&mut self, lumens : f64, area : f64) {
+        self.set_power(lumens / LUMENS_PER_WATT, area);
+    }
+
+    // Like the vertical angles and candela of an IES file, only the shape of the profile matters.
+    // A profile with no positive intensity gives no light
+    pub fn set_profile(&mut self, profile : &[(f64, f64)]) {
+        let mut profile = profile.to_vec();
+        profile.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let peak = profile.iter().fold(0.0, |peak : f64, entry| peak.max(entry.1));
+        let normalise = if peak > 0.0 { 1.0 / peak } else { 0.0 };
+        self.profile = profile.iter().map(|(angle, intensity)| (*angle, intensity * normalise)).collect();
+        let step = 0.5 * PI / PROFILE_STEPS as f64;
+        self.profile_integral = (0..PROFILE_STEPS).map(|i| {
+            let theta = (i as f64 + 0.5) * step;
+            2.0 * PI * self.falloff(theta.cos()) * theta.cos() * theta.sin() * step
+        }).sum();
+    }
+
+    // Profile interpolated at the angle, no light past its last angle
+    fn falloff(&self, cos_th : f64) -> f64 {
+        if self.profile.is_empty() {
+            return 1.0;
+        }
+        let angle = cos_th.clamp(-1.0, 1.0).acos().to_degrees();
+        let after = self.profile.partition_point(|entry| entry.0 < angle);
+        if after == 0 {
+            return self.profile[0].1;
+        }
+        if after == self.profile.len() {
+            return 0.0;
+        }
+        let (angle_0, intensity_0) = self.profile[after - 1];
+        let (angle_1, intensity_1) = self.profile[after];
+        let s = (angle - angle_0) / (angle_1 - angle_0);
+        (1.0 - s) * intensity_0 + s * intensity_1
+    }
+
+    fn scale(&self) -> f64 {
+        match self.power {
+            // Nothing to share the power out over when the profile is dark
+            Some(_) if self.profile_integral <= 0.0 => 0.0,
+            Some((power, area)) => {
+                let sides = if self.two_sided { 2.0 } else { 1.0 };
+                power / (sides * area * self.profile_integral)
+            },
+            None => 1.0
+        }
     }
 }
 
@@ -120,8 +488,13 @@ impl<'tex> Material for DiffuseLight<'tex> {
         None
     }
 
-    fn emitted(&self, u: f64, v: f64, point: &Point3) -> Colour {
-        self.light.value(u, v, point)
+    fn emitted(&self, ray_in : &Ray, hit_record : &HitRecord) -> Colour {
+        if !self.two_sided && !hit_record.front_face {
+            return Colour::zero();
+        }
+        let cos_th = dot(&-ray_in.direction.unit(), &hit_record.normal);
+        let light = hit_record.texture_value(self.light);
+        (self.scale() * self.falloff(cos_th)) * light
     }
 }
 
@@ -138,6 +511,559 @@ impl<'tex> Isotropic<'tex> {
 impl<'tex> Material for Isotropic<'tex> {
     fn scatter(&self, rng: &mut ThreadRng, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
         let scatter_direction = random_unit(rng);
-        Some( (self.colour.value(hit_record.u, hit_record.v, &hit_record.point), Ray::new(&hit_record.point, &scatter_direction, ray_in.time)) )
+        Some( (hit_record.texture_value(self.colour), Ray::new(&hit_record.point, &scatter_direction, ray_in.time)) )
+    }
+
+    fn evaluate(&self, _ray_in : &Ray, hit_record : &HitRecord, _direction : &Vec3) -> Option<(Colour, f64)> {
+        let colour = hit_record.texture_value(self.colour);
+        Some((colour / (4.0 * PI), 1.0 / (4.0 * PI)))
+    }
+}
+
+// Rough metal with complex index of refraction eta + ik per colour channel
+pub struct Conductor<'tex> {
+    eta : Colour,
+    k : Colour,
+    distribution : Ggx,
+    film : Option<ThinFilm<'tex>>
+}
+
+impl<'tex> Conductor<'tex> {
+    // Roughness along the surface's u tangent and across it, isotropic where the surface has no tangents
+    pub fn new(eta : &Colour, k : &Colour, roughness_u : f64, roughness_v : f64) -> Conductor<'tex> {
+        Conductor {
+            eta : *eta,
+            k : *k,
+            distribution : Ggx::new(roughness_u, roughness_v),
+            film : None
+        }
+    }
+
+    pub fn set_thin_film(&mut self, film : ThinFilm<'tex>) {
+        self.film = Some(film);
+    }
+
+    fn fresnel(&self, ray_in : &Ray, hit_record : &HitRecord, cos_th : f64) -> Colour {
+        match &self.film {
+            Some(film) => film.reflectance(hit_record, ray_in.wavelength, cos_th, 1.0, &self.eta, &self.k),
+            None => fresnel_conductor_colour(cos_th, &self.eta, &self.k)
+        }
+    }
+
+    pub fn gold(roughness : f64) -> Conductor<'tex> {
+        Conductor::new(&Colour::new(0.143, 0.374, 1.442), &Colour::new(3.983, 2.385, 1.603), roughness, roughness)
+    }
+
+    pub fn silver(roughness : f64) -> Conductor<'tex> {
+        Conductor::new(&Colour::new(0.155, 0.117, 0.138), &Colour::new(4.828, 3.122, 2.147), roughness, roughness)
+    }
+
+    pub fn copper(roughness : f64) -> Conductor<'tex> {
+        Conductor::new(&Colour::new(0.200, 0.924, 1.102), &Colour::new(3.912, 2.452, 2.142), roughness, roughness)
+    }
+
+    pub fn aluminium(roughness : f64) -> Conductor<'tex> {
+        Conductor::new(&Colour::new(1.657, 0.880, 0.521), &Colour::new(9.224, 6.270, 4.837), roughness, roughness)
+    }
+
+    pub fn iron(roughness : f64) -> Conductor<'tex> {
+        Conductor::new(&Colour::new(2.911, 2.950, 2.585), &Colour::new(3.089, 2.932, 2.767), roughness, roughness)
+    }
+}
+
+impl<'tex> Material for Conductor<'tex> {
+    fn scatter(&self, rng: &mut ThreadRng, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
+        let frame = OrthonormalBasis::with_tangent(&hit_record.normal, &hit_record.dpdu);
+        let wo = frame.to_local(&-ray_in.direction.unit());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+        if self.distribution.is_smooth() {
+            let wi = Vec3::new(-wo.x(), -wo.y(), wo.z());
+            let colour = self.fresnel(ray_in, hit_record, wo.z());
+            return Some((colour, Ray::new(&hit_record.point, &frame.to_world(&wi), ray_in.time)));
+        }
+        let wm = self.distribution.sample_visible_normal(rng, &wo);
+        let wi = reflect(&-wo, &wm);
+        if wi.z() <= 0.0 {
+            return None;
+        }
+        let fresnel = self.fresnel(ray_in, hit_record, dot(&wo, &wm));
+        let weight = self.distribution.masking_shadowing(&wo, &wi) / self.distribution.masking(&wo);
+        Some((fresnel * weight, Ray::new(&hit_record.point, &frame.to_world(&wi), ray_in.time)))
+    }
+
+    fn evaluate(&self, ray_in : &Ray, hit_record : &HitRecord, direction : &Vec3) -> Option<(Colour, f64)> {
+        if self.distribution.is_smooth() {
+            return None;
+        }
+        let frame = OrthonormalBasis::with_tangent(&hit_record.normal, &hit_record.dpdu);
+        let wo = frame.to_local(&-ray_in.direction.unit());
+        let wi = frame.to_local(&direction.unit());
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Some((Colour::zero(), 0.0));
+        }
+        let wm = (wo + wi).unit();
+        let d = self.distribution.distribution(&wm);
+        let fresnel = self.fresnel(ray_in, hit_record, dot(&wo, &wm));
+        let g = self.distribution.masking_shadowing(&wo, &wi);
+        let pdf = self.distribution.visible_pdf(&wo, &wm) / (4.0 * dot(&wo, &wm));
+        Some((fresnel * (d * g / (4.0 * wo.z())), pdf))
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.film.is_some()
+    }
+}
+
+// Frosted glass, GGX microfacets for both reflection and transmission (Walter et al. 2007)
+pub struct RoughDielectric {
+    refraction_index : f64,
+    distribution : Ggx,
+    absorption : Colour
+}
+
+impl RoughDielectric {
+    pub fn new(refraction_index : f64, roughness : f64) -> RoughDielectric {
+        RoughDielectric::absorbing(refraction_index, roughness, &Colour::zero())
+    }
+
+    pub fn absorbing(refraction_index : f64, roughness : f64, absorption : &Colour) -> RoughDielectric {
+        RoughDielectric {
+            refraction_index,
+            distribution : Ggx::new(roughness, roughness),
+            absorption : *absorption
+        }
+    }
+
+    // Index of the side being entered over the side being left
+    fn eta(&self, hit_record : &HitRecord) -> f64 {
+        if hit_record.front_face {
+            self.refraction_index
+        } else {
+            1.0 / self.refraction_index
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, rng: &mut ThreadRng, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
+        let frame = OrthonormalBasis::new(&hit_record.normal);
+        let wo = frame.to_local(&-ray_in.direction.unit());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+        let (weight, wi) = sample_dielectric(rng, &self.distribution, self.eta(hit_record), &wo)?;
+        Some((
+            weight * transmittance(&self.absorption, ray_in, hit_record),
+            Ray::new(&hit_record.point, &frame.to_world(&wi), ray_in.time)
+        ))
+    }
+
+    fn evaluate(&self, ray_in : &Ray, hit_record : &HitRecord, direction : &Vec3) -> Option<(Colour, f64)> {
+        if self.distribution.is_smooth() {
+            return None;
+        }
+        let frame = OrthonormalBasis::new(&hit_record.normal);
+        let wo = frame.to_local(&-ray_in.direction.unit());
+        let wi = frame.to_local(&direction.unit());
+        let (value, pdf) = evaluate_dielectric(&self.distribution, self.eta(hit_record), &wo, &wi);
+        Some((value * transmittance(&self.absorption, ray_in, hit_record), pdf))
+    }
+}
+
+// Picks between two materials at each hit, weight is the chance of the second
+pub struct MixMaterial<'mat> {
+    first : &'mat dyn Material,
+    second : &'mat dyn Material,
+    weight : Scalar<'mat>
+}
+
+impl<'mat> MixMaterial<'mat> {
+    pub fn new(first : &'mat dyn Material, second : &'mat dyn Material, weight : Scalar<'mat>) -> MixMaterial<'mat> {
+        MixMaterial { first, second, weight }
+    }
+
+    // Layer picked by the hit's layer sample, so the hit test and the shading agree. The sample is
+    // stretched back over [0, 1) for the chosen layer so mixes inside it choose independently
+    fn choose<'a>(&self, hit_record : &HitRecord<'a>) -> (&'mat dyn Material, HitRecord<'a>) {
+        let weight = self.weight.value_at_hit(hit_record).clamp(0.0, 1.0);
+        let sample = hit_record.layer_sample;
+        if sample < weight {
+            (self.second, HitRecord { layer_sample : sample / weight, ..*hit_record })
+        } else {
+            (self.first, HitRecord { layer_sample : (sample - weight) / (1.0 - weight), ..*hit_record })
+        }
+    }
+}
+
+impl<'mat> Material for MixMaterial<'mat> {
+    // Choosing by the weight cancels it out of the scattered colour
+    fn scatter(&self, rng: &mut ThreadRng, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
+        let (layer, hit_record) = self.choose(hit_record);
+        layer.scatter(rng, ray_in, &hit_record)
+    }
+
+    fn emitted(&self, ray_in : &Ray, hit_record : &HitRecord) -> Colour {
+        let (layer, hit_record) = self.choose(hit_record);
+        layer.emitted(ray_in, &hit_record)
+    }
+
+    fn evaluate(&self, ray_in : &Ray, hit_record : &HitRecord, direction : &Vec3) -> Option<(Colour, f64)> {
+        let (layer, hit_record) = self.choose(hit_record);
+        layer.evaluate(ray_in, &hit_record, direction)
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.first.is_dispersive() || self.second.is_dispersive()
+    }
+
+    // Missing where the chosen layer is missing, the hits left are shaded by that same layer
+    fn cutout(&self, rng : &mut ThreadRng, hit_record : &HitRecord) -> bool {
+        let (layer, hit_record) = self.choose(hit_record);
+        layer.cutout(rng, &hit_record)
+    }
+}
+
+// How the alpha of an opacity texture removes a surface
+#[derive(Copy, Clone)]
+pub enum AlphaMask {
+    // Missing wherever alpha is below the threshold
+    Threshold(f64),
+    // Missing with probability one minus alpha so partial coverage blends
+    Stochastic
+}
+
+// Material with holes where the alpha of the opacity texture is low, like leaves or fences
+pub struct Cutout<'mat> {
+    material : &'mat dyn Material,
+    opacity : &'mat dyn Texture,
+    mask : AlphaMask
+}
+
+impl<'mat> Cutout<'mat> {
+    pub fn new(material : &'mat dyn Material, opacity : &'mat dyn Texture, mask : AlphaMask) -> Cutout<'mat> {
+        Cutout {
+            material,
+            opacity,
+            mask
+        }
+    }
+}
+
+impl<'mat> Material for Cutout<'mat> {
+    fn scatter(&self, rng : &mut ThreadRng, ray_in : &Ray, hit_record : &HitRecord) -> Option<(Colour, Ray)> {
+        self.material.scatter(rng, ray_in, hit_record)
+    }
+
+    fn emitted(&self, ray_in : &Ray, hit_record : &HitRecord) -> Colour {
+        self.material.emitted(ray_in, hit_record)
+    }
+
+    fn evaluate(&self, ray_in : &Ray, hit_record : &HitRecord, direction : &Vec3) -> Option<(Colour, f64)> {
+        self.material.evaluate(ray_in, hit_record, direction)
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.material.is_dispersive()
+    }
+
+    fn subsurface<'a>(&'a self, rng : &mut ThreadRng, ray_in : &Ray, hit_record : &HitRecord) -> Option<(Colour, HitRecord<'a>)> {
+        self.material.subsurface(rng, ray_in, hit_record)
+    }
+
+    fn cutout(&self, rng : &mut ThreadRng, hit_record : &HitRecord) -> bool {
+        let alpha = self.opacity.alpha_at_hit(hit_record);
+        let missing = match self.mask {
+            AlphaMask::Threshold(threshold) => alpha < threshold,
+            AlphaMask::Stochastic => rng.gen::<f64>() >= alpha
+        };
+        missing || self.material.cutout(rng, hit_record)
+    }
+}
+
+// Clear dielectric layer such as varnish over another material, light passes through the
+// coat once each way and is absorbed according to the distance travelled in it
+pub struct Coated<'mat> {
+    base : &'mat dyn Material,
+    refraction_index : f64,
+    distribution : Ggx,
+    thickness : f64,
+    absorption : Colour
+}
+
+impl<'mat> Coated<'mat> {
+    // Coat roughness is kept above zero so lights can still be sampled through it
+    pub fn new(base : &'mat dyn Material, refraction_index : f64, roughness : f64, thickness : f64, absorption : &Colour) -> Coated<'mat> {
+        Coated {
+            base,
+            refraction_index,
+            distribution : Ggx::new(roughness.max(0.05), roughness.max(0.05)),
+            thickness,
+            absorption : *absorption
+        }
+    }
+
+    // Direction inside the coat, pointing away from the base, for a direction outside
+    fn inside(&self, w : &Vec3) -> Vec3 {
+        let eta = self.refraction_index;
+        let sin2 = (1.0 - w.z() * w.z()) / (eta * eta);
+        Vec3::new(w.x() / eta, w.y() / eta, (1.0 - sin2).max(0.0).sqrt())
+    }
+
+    // Direction leaving the coat, None if it is totally internally reflected
+    fn outside(&self, w : &Vec3) -> Option<Vec3> {
+        let eta = self.refraction_index;
+        let sin2 = (1.0 - w.z() * w.z()) * eta * eta;
+        if sin2 >= 1.0 {
+            return None;
+        }
+        Some(Vec3::new(w.x() * eta, w.y() * eta, (1.0 - sin2).sqrt()))
+    }
+
+    // Fresnel transmission both ways and absorption along the path to the base and back
+    fn through_coat(&self, wo : &Vec3, wi : &Vec3, wo_inside : &Vec3, wi_inside : &Vec3) -> Colour {
+        let eta = self.refraction_index;
+        let fresnel = (1.0 - fresnel_dielectric(wo.z(), eta)) * (1.0 - fresnel_dielectric(wi.z(), eta));
+        let distance = self.thickness * (1.0 / wo_inside.z() + 1.0 / wi_inside.z());
+        fresnel * Colour::new(
+            (-self.absorption.x() * distance).exp(),
+            (-self.absorption.y() * distance).exp(),
+            (-self.absorption.z() * distance).exp()
+        )
+    }
+}
+
+impl<'mat> Material for Coated<'mat> {
+    fn scatter(&self, rng: &mut ThreadRng, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
+        let frame = OrthonormalBasis::new(&hit_record.normal);
+        let wo = frame.to_local(&-ray_in.direction.unit());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+        let coat_probability = fresnel_dielectric(wo.z(), self.refraction_index);
+        if rng.gen::<f64>() < coat_probability {
+            let wm = self.distribution.sample_visible_normal(rng, &wo);
+            let wi = reflect(&-wo, &wm);
+            if wi.z() <= 0.0 {
+                return None;
+            }
+            let fresnel = fresnel_dielectric(dot(&wo, &wm), self.refraction_index);
+            let weight = fresnel * self.distribution.masking_shadowing(&wo, &wi)
+                / (self.distribution.masking(&wo) * coat_probability);
+            return Some((
+                Colour::new(weight, weight, weight),
+                Ray::new(&hit_record.point, &frame.to_world(&wi), ray_in.time)
+            ));
+        }
+        // Refract down to the base, scatter there and back out through the coat
+        let wo_inside = self.inside(&wo);
+        let mut base_ray = Ray::new(&hit_record.point, &frame.to_world(&-wo_inside), ray_in.time);
+        base_ray.wavelength = ray_in.wavelength;
+        let (base_colour, scattered) = self.base.scatter(rng, &base_ray, hit_record)?;
+        let wi_inside = frame.to_local(&scattered.direction.unit());
+        if wi_inside.z() <= 0.0 {
+            return None;
+        }
+        let wi = self.outside(&wi_inside)?;
+        let coat = self.through_coat(&wo, &wi, &wo_inside, &wi_inside) / (1.0 - coat_probability);
+        Some((
+            attenuate(&coat, &base_colour),
+            Ray::new(&hit_record.point, &frame.to_world(&wi), ray_in.time)
+        ))
+    }
+
+    fn evaluate(&self, ray_in : &Ray, hit_record : &HitRecord, direction : &Vec3) -> Option<(Colour, f64)> {
+        let frame = OrthonormalBasis::new(&hit_record.normal);
+        let wo = frame.to_local(&-ray_in.direction.unit());
+        let wi = frame.to_local(&direction.unit());
+        let wo_inside = self.inside(&wo);
+        let wi_inside = self.inside(&wi);
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Some((Colour::zero(), 0.0));
+        }
+        let mut base_ray = Ray::new(&hit_record.point, &frame.to_world(&-wo_inside), ray_in.time);
+        base_ray.wavelength = ray_in.wavelength;
+        let eta = self.refraction_index;
+        let coat_probability = fresnel_dielectric(wo.z(), eta);
+        let mut value = Colour::zero();
+        let mut pdf = 0.0;
+        // A base that only scatters in discrete directions adds nothing here so lights still see the coat
+        if let Some((base, base_pdf)) = self.base.evaluate(&base_ray, hit_record, &frame.to_world(&wi_inside)) {
+            // Solid angle is compressed entering the coat
+            let jacobian = wi.z() / (eta * eta * wi_inside.z());
+            value += jacobian * attenuate(&self.through_coat(&wo, &wi, &wo_inside, &wi_inside), &base);
+            pdf += (1.0 - coat_probability) * base_pdf * jacobian;
+        }
+        let wm = (wo + wi).unit();
+        let cos_om = dot(&wo, &wm);
+        if cos_om > 0.0 {
+            let d = self.distribution.distribution(&wm);
+            let g = self.distribution.masking_shadowing(&wo, &wi);
+            let fresnel = fresnel_dielectric(cos_om, eta);
+            value += Colour::new(1.0, 1.0, 1.0) * (d * g * fresnel / (4.0 * wo.z()));
+            pdf += coat_probability * self.distribution.visible_pdf(&wo, &wm) / (4.0 * cos_om);
+        }
+        Some((value, pdf))
+    }
+
+    fn emitted(&self, ray_in : &Ray, hit_record : &HitRecord) -> Colour {
+        self.base.emitted(ray_in, hit_record)
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.base.is_dispersive()
+    }
+
+    // Holes in the base go through the coat too
+    fn cutout(&self, rng : &mut ThreadRng, hit_record : &HitRecord) -> bool {
+        self.base.cutout(rng, hit_record)
+    }
+}
+
+// Perfect mirror used for light reflecting off the surface of a subsurface material
+struct SpecularReflection;
+
+impl Material for SpecularReflection {
+    fn scatter(&self, _rng: &mut ThreadRng, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
+        let direction = reflect(&ray_in.direction, &hit_record.normal);
+        Some((Colour::new(1.0, 1.0, 1.0), Ray::new(&hit_record.point, &direction, ray_in.time)))
+    }
+}
+
+// White lambertian lobe for light leaving a subsurface material, the walk supplies the colour
+struct DiffuseExit;
+
+impl Material for DiffuseExit {
+    fn scatter(&self, rng: &mut ThreadRng, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
+        let direction = cosine_direction(rng, &hit_record.normal);
+        Some((Colour::new(1.0, 1.0, 1.0), Ray::new(&hit_record.point, &direction, ray_in.time)))
+    }
+
+    fn evaluate(&self, _ray_in : &Ray, hit_record : &HitRecord, direction : &Vec3) -> Option<(Colour, f64)> {
+        let cos_th = dot(&hit_record.normal, &direction.unit()).max(0.0);
+        Some((Colour::new(1.0, 1.0, 1.0) * (cos_th / PI), cos_th / PI))
+    }
+}
+
+static SPECULAR_REFLECTION : SpecularReflection = SpecularReflection;
+static DIFFUSE_EXIT : DiffuseExit = DiffuseExit;
+
+// Longest random walk before the light is treated as absorbed
+const MAX_WALK_STEPS : u32 = 256;
+
+fn channels(colour : &Colour) -> [f64; 3] {
+    [colour.x(), colour.y(), colour.z()]
+}
+
+fn channel_exp(colour : &Colour, distance : f64) -> Colour {
+    Colour::new((-colour.x() * distance).exp(), (-colour.y() * distance).exp(), (-colour.z() * distance).exp())
+}
+
+fn channel_average(colour : &Colour) -> f64 {
+    (colour.x() + colour.y() + colour.z()) / 3.0
+}
+
+// Skin, wax, marble and milk, light enters the closed boundary diffusely and random walks
+// through an isotropic medium until it leaves again. Coefficients are per unit distance
+pub struct Subsurface<'a> {
+    boundary : Box<dyn Hittable + 'a>,
+    scattering : Colour,
+    extinction : Colour,
+    refraction_index : f64
+}
+
+impl<'a> Subsurface<'a> {
+    pub fn new(boundary : Box<dyn Hittable + 'a>, scattering : &Colour, absorption : &Colour, refraction_index : f64) -> Subsurface<'a> {
+        Subsurface {
+            boundary,
+            scattering : *scattering,
+            extinction : scattering + absorption,
+            refraction_index
+        }
+    }
+
+    // Colour seen after many bounces and the average distance light travels per channel,
+    // using the fit from Chiang et al. 2016
+    pub fn from_albedo(boundary : Box<dyn Hittable + 'a>, albedo : &Colour, mean_free_path : &Colour, refraction_index : f64) -> Subsurface<'a> {
+        let single = |a : f64| 1.0 - (a * (-5.09406 + a * (2.61188 - a * 4.31805))).exp();
+        let extinction = |a : f64, d : f64| {
+            let s = 1.9 - a + 3.5 * (a - 0.8) * (a - 0.8);
+            1.0 / (d * s).max(1e-8)
+        };
+        let extinction = Colour::new(
+            extinction(albedo.x(), mean_free_path.x()),
+            extinction(albedo.y(), mean_free_path.y()),
+            extinction(albedo.z(), mean_free_path.z())
+        );
+        let scattering = attenuate(&Colour::new(single(albedo.x()), single(albedo.y()), single(albedo.z())), &extinction);
+        Subsurface { boundary, scattering, extinction, refraction_index }
+    }
+
+    pub fn boundary(&self) -> &dyn Hittable {
+        self.boundary.as_ref()
+    }
+
+    // Walk from a point on the boundary, None if the light is lost inside
+    fn walk(&self, rng : &mut ThreadRng, ray_in : &Ray, hit_record : &HitRecord) -> Option<(Colour, HitRecord<'_>)> {
+        let extinction = channels(&self.extinction);
+        let mut origin = hit_record.point;
+        let mut direction = cosine_direction(rng, &-hit_record.normal);
+        let mut weight = Colour::new(1.0, 1.0, 1.0);
+        for _ in 0..MAX_WALK_STEPS {
+            // Pick a channel to sample distance with and weight by the average over channels
+            let channel = rng.gen_range(0..3);
+            let distance = -(1.0 - rng.gen::<f64>()).ln() / extinction[channel];
+            let ray = Ray::new(&origin, &direction, ray_in.time);
+            // Normal of the exit faces back inside towards the walk
+            let exit = self.boundary.hit(&ray, &Interval::new(1e-4, f64::MAX), rng)?;
+            if exit.t <= distance {
+                let transmittance = channel_exp(&self.extinction, exit.t);
+                weight = attenuate(&weight, &transmittance) / channel_average(&transmittance);
+                let record = HitRecord {
+                    point : exit.point,
+                    normal : -exit.normal,
+                    t : exit.t,
+                    u : exit.u,
+                    v : exit.v,
+                    front_face : true,
+                    time : exit.time,
+                    object_point : exit.object_point,
+                    object_normal : exit.object_normal,
+                    dpdu : exit.dpdu,
+                    dpdv : exit.dpdv,
+                    object_dpdu : exit.object_dpdu,
+                    object_dpdv : exit.object_dpdv,
+                    footprint : None,
+                    layer_sample : exit.layer_sample,
+                    material : &DIFFUSE_EXIT
+                };
+                return Some((weight, record));
+            }
+            let transmittance = channel_exp(&self.extinction, distance);
+            let pdf = channel_average(&attenuate(&self.extinction, &transmittance));
+            weight = attenuate(&weight, &attenuate(&self.scattering, &transmittance)) / pdf;
+            origin = ray.at(distance);
+            direction = random_unit(rng);
+        }
+        None
+    }
+}
+
+impl<'a> Material for Subsurface<'a> {
+    fn scatter(&self, rng: &mut ThreadRng, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Colour, Ray)> {
+        let (weight, exit) = self.subsurface(rng, ray_in, hit_record)?;
+        let (colour, ray) = exit.material.scatter(rng, ray_in, &exit)?;
+        Some((attenuate(&weight, &colour), ray))
+    }
+
+    fn subsurface<'b>(&'b self, rng : &mut ThreadRng, ray_in : &Ray, hit_record : &HitRecord) -> Option<(Colour, HitRecord<'b>)> {
+        let cos_th = dot(&-ray_in.direction.unit(), &hit_record.normal);
+        let mirror = HitRecord { material : &SPECULAR_REFLECTION, ..*hit_record };
+        if rng.gen::<f64>() < fresnel_dielectric(cos_th, self.refraction_index) {
+            return Some((Colour::new(1.0, 1.0, 1.0), mirror));
+        }
+        let lost = HitRecord { material : &DIFFUSE_EXIT, ..*hit_record };
+        Some(self.walk(rng, ray_in, hit_record).unwrap_or((Colour::zero(), lost)))
     }
 }
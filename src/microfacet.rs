@@ -0,0 +1,278 @@
+use std::f64::consts::PI;
+use rand::Rng;
+use crate::colour::Colour;
+use crate::vec3::{cross, dot, reflect, refract, UnitVec3, Vec3};
+
+// Below this alpha surfaces are treated as perfectly smooth
+pub const SMOOTH_ALPHA : f64 = 1e-3;
+
+// Trowbridge-Reitz distribution in a local frame with the normal along z
+#[derive(Copy, Clone)]
+pub struct Ggx {
+    alpha_x : f64,
+    alpha_y : f64
+}
+
+impl Ggx {
+    // Perceptual roughness in [0, 1] along the two tangent directions
+    pub fn new(roughness_x : f64, roughness_y : f64) -> Ggx {
+        Ggx {
+            alpha_x : roughness_x.clamp(0.0, 1.0).powi(2),
+            alpha_y : roughness_y.clamp(0.0, 1.0).powi(2)
+        }
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < SMOOTH_ALPHA
+    }
+
+    pub fn distribution(&self, wm : &UnitVec3) -> f64 {
+        if wm.z() <= 0.0 {
+            return 0.0;
+        }
+        let x = wm.x() / self.alpha_x;
+        let y = wm.y() / self.alpha_y;
+        let denom = x * x + y * y + wm.z() * wm.z();
+        1.0 / (PI * self.alpha_x * self.alpha_y * denom * denom)
+    }
+
+    fn lambda(&self, w : &UnitVec3) -> f64 {
+        let z2 = w.z() * w.z();
+        if z2 <= 0.0 {
+            return f64::MAX;
+        }
+        let a2 = self.alpha_x * self.alpha_x * w.x() * w.x() + self.alpha_y * self.alpha_y * w.y() * w.y();
+        0.5 * (-1.0 + (1.0 + a2 / z2).sqrt())
+    }
+
+    pub fn masking(&self, w : &UnitVec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    pub fn masking_shadowing(&self, wo : &UnitVec3, wi : &UnitVec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // Density of sample_visible_normal choosing wm as seen from wo
+    pub fn visible_pdf(&self, wo : &UnitVec3, wm : &UnitVec3) -> f64 {
+        let cos_o = wo.z().abs();
+        if cos_o <= 0.0 {
+            return 0.0;
+        }
+        self.masking(wo) * self.distribution(wm) * (wo.x() * wm.x() + wo.y() * wm.y() + wo.z() * wm.z()).abs() / cos_o
+    }
+
+    // Heitz 2018, sample normals visible from wo which must be above the surface
+    pub fn sample_visible_normal<R>(&self, rng : &mut R, wo : &UnitVec3) -> UnitVec3
+    where R : Rng
+    {
+        let vh = Vec3::new(self.alpha_x * wo.x(), self.alpha_y * wo.y(), wo.z()).unit();
+        let len_sq = vh.x() * vh.x() + vh.y() * vh.y();
+        let t1 = if len_sq > 0.0 {
+            Vec3::new(-vh.y(), vh.x(), 0.0) / len_sq.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = cross(&vh, &t1);
+        let r = rng.gen::<f64>().sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z());
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+        Vec3::new(self.alpha_x * nh.x(), self.alpha_y * nh.y(), nh.z().max(1e-6)).unit()
+    }
+}
+
+// Exact Fresnel reflectance for a complex index of refraction eta + ik
+pub fn fresnel_conductor(cos_i : f64, eta : f64, k : f64) -> f64 {
+    let cos2 = cos_i * cos_i;
+    let sin2 = 1.0 - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+    let t0 = eta2 - k2 - sin2;
+    let a2b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2b2 + cos2;
+    let a = (0.5 * (a2b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    0.5 * (rp + rs)
+}
+
+pub fn fresnel_conductor_colour(cos_i : f64, eta : &Colour, k : &Colour) -> Colour {
+    Colour::new(
+        fresnel_conductor(cos_i, eta.x(), k.x()),
+        fresnel_conductor(cos_i, eta.y(), k.y()),
+        fresnel_conductor(cos_i, eta.z(), k.z())
+    )
+}
+
+// Exact Fresnel reflectance for unpolarised light, eta is the transmitted over incident index
+pub fn fresnel_dielectric(cos_i : f64, eta : f64) -> f64 {
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+// Sample reflection or transmission through a rough dielectric boundary (Walter et al. 2007),
+// eta is the index of the side being entered over the side being left.
+// Gives the direction and the sample weight, f times cosine over pdf
+pub fn sample_dielectric<R>(rng : &mut R, distribution : &Ggx, eta : f64, wo : &UnitVec3) -> Option<(f64, UnitVec3)>
+where R : Rng
+{
+    let wm = if distribution.is_smooth() {
+        Vec3::new(0.0, 0.0, 1.0)
+    } else {
+        distribution.sample_visible_normal(rng, wo)
+    };
+    let fresnel = fresnel_dielectric(dot(wo, &wm), eta);
+    let wi = if rng.gen::<f64>() < fresnel {
+        reflect(&-wo, &wm)
+    } else {
+        refract(&-wo, &wm, 1.0 / eta)
+    };
+    // Sampled microfacet sends the ray to the wrong side of the surface
+    let reflected = wi.z() > 0.0;
+    if reflected != (dot(&wi, &wm) > 0.0) {
+        return None;
+    }
+    let weight = if distribution.is_smooth() {
+        1.0
+    } else {
+        distribution.masking_shadowing(wo, &wi) / distribution.masking(wo)
+    };
+    Some((weight, wi))
+}
+
+// Scattering times the cosine and the pdf of sample_dielectric choosing wi
+pub fn evaluate_dielectric(distribution : &Ggx, eta : f64, wo : &UnitVec3, wi : &UnitVec3) -> (f64, f64) {
+    if wo.z() <= 0.0 || wi.z() == 0.0 {
+        return (0.0, 0.0);
+    }
+    let reflected = wi.z() > 0.0;
+    // Generalised half vector on the side of the normal
+    let mut wm = if reflected { wo + wi } else { wo + eta * wi };
+    if wm.length_squared() == 0.0 {
+        return (0.0, 0.0);
+    }
+    wm = wm.unit();
+    if wm.z() < 0.0 {
+        wm = -wm;
+    }
+    let cos_om = dot(wo, &wm);
+    let cos_im = dot(wi, &wm);
+    if cos_om <= 0.0 || (cos_im > 0.0) != reflected {
+        return (0.0, 0.0);
+    }
+    let d = distribution.distribution(&wm);
+    let g = distribution.masking_shadowing(wo, wi);
+    let fresnel = fresnel_dielectric(cos_om, eta);
+    let visible = distribution.visible_pdf(wo, &wm);
+    if reflected {
+        (d * g * fresnel / (4.0 * wo.z()), visible / (4.0 * cos_om) * fresnel)
+    } else {
+        let denom = (cos_im + cos_om / eta).powi(2);
+        (
+            d * g * (1.0 - fresnel) * (cos_im * cos_om).abs() / (wo.z() * denom),
+            visible * cos_im.abs() / denom * (1.0 - fresnel)
+        )
+    }
+}
+
+// Just enough complex arithmetic for thin film interference
+#[derive(Copy, Clone)]
+struct Complex {
+    re : f64,
+    im : f64
+}
+
+impl Complex {
+    fn new(re : f64, im : f64) -> Complex {
+        Complex { re, im }
+    }
+
+    fn add(&self, other : &Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+
+    fn sub(&self, other : &Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+
+    fn mul(&self, other : &Complex) -> Complex {
+        Complex::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+    }
+
+    fn div(&self, other : &Complex) -> Complex {
+        let denom = other.norm_squared();
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / denom,
+            (self.im * other.re - self.re * other.im) / denom
+        )
+    }
+
+    fn norm_squared(&self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    // Principal root
+    fn sqrt(&self) -> Complex {
+        let r = self.norm_squared().sqrt();
+        let re = (0.5 * (r + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (r - self.re)).max(0.0).sqrt();
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    // e to the i times self
+    fn exp_i(&self) -> Complex {
+        let scale = (-self.im).exp();
+        Complex::new(scale * self.re.cos(), scale * self.re.sin())
+    }
+}
+
+// Cosine of the angle in a layer of index n for light at sin_i from a layer of index n_i
+fn layer_cosine(n_i : f64, sin_i : f64, n : &Complex) -> Complex {
+    let s = Complex::new(n_i * sin_i, 0.0).div(n);
+    Complex::new(1.0, 0.0).sub(&s.mul(&s)).sqrt()
+}
+
+// Amplitude reflection between two layers for s and p polarisation
+fn amplitude_reflection(n_i : &Complex, cos_i : &Complex, n_t : &Complex, cos_t : &Complex) -> (Complex, Complex) {
+    let a = n_i.mul(cos_i);
+    let b = n_t.mul(cos_t);
+    let c = n_t.mul(cos_i);
+    let d = n_i.mul(cos_t);
+    (a.sub(&b).div(&a.add(&b)), c.sub(&d).div(&c.add(&d)))
+}
+
+// Reflectance of a film of thickness and index over a base of complex index eta + ik for light
+// arriving from a medium of index incident, summing the interfering reflections (Airy).
+// Thickness and wavelength in nanometres
+pub fn fresnel_thin_film(cos_i : f64, incident : f64, thickness : f64, film : f64, eta : f64, k : f64, wavelength : f64) -> f64 {
+    let sin_i = (1.0 - cos_i * cos_i).max(0.0).sqrt();
+    let n0 = Complex::new(incident, 0.0);
+    let n1 = Complex::new(film, 0.0);
+    let n2 = Complex::new(eta, k);
+    let cos0 = Complex::new(cos_i, 0.0);
+    let cos1 = layer_cosine(incident, sin_i, &n1);
+    let cos2 = layer_cosine(incident, sin_i, &n2);
+    let (r01_s, r01_p) = amplitude_reflection(&n0, &cos0, &n1, &cos1);
+    let (r12_s, r12_p) = amplitude_reflection(&n1, &cos1, &n2, &cos2);
+    // Phase difference of a round trip through the film
+    let scale = 4.0 * PI * thickness / wavelength;
+    let phase = n1.mul(&cos1).mul(&Complex::new(scale, 0.0)).exp_i();
+    let airy = |r01 : &Complex, r12 : &Complex| {
+        let delayed = r12.mul(&phase);
+        let one = Complex::new(1.0, 0.0);
+        r01.add(&delayed).div(&one.add(&r01.mul(&delayed))).norm_squared()
+    };
+    (0.5 * (airy(&r01_s, &r12_s) + airy(&r01_p, &r12_p))).clamp(0.0, 1.0)
+}
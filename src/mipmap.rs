@@ -0,0 +1,280 @@
+use image::Rgba32FImage;
+use crate::colour::Colour;
+use crate::textures::Footprint;
+
+// Longest the major axis of an EWA ellipse may be against the minor axis
+const MAX_ANISOTROPY : f64 = 8.0;
+// Falloff of the gaussian weights across an EWA ellipse
+const EWA_ALPHA : f64 = 2.0;
+
+// Colour and alpha of a texel
+pub type Texel = [f64; 4];
+
+// Reconstruction between texels
+#[derive(Copy, Clone)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+    // Catmull-Rom over the 4x4 neighbouring texels
+    Bicubic
+}
+
+// How texture coordinates outside [0, 1] are mapped back onto the image
+#[derive(Copy, Clone)]
+pub enum Wrap {
+    Repeat,
+    Mirror,
+    Clamp,
+    // Opaque colour outside the image
+    Border(Colour)
+}
+
+// Averaging over the footprint of a pixel
+#[derive(Copy, Clone)]
+pub enum MipFilter {
+    // Always the full resolution image
+    Off,
+    // Blend of the two levels nearest the footprint width
+    Trilinear,
+    // Elliptically weighted average over the anisotropic footprint
+    Ewa
+}
+
+fn wrap_index(index : i64, size : usize, wrap : &Wrap) -> Option<usize> {
+    let size = size as i64;
+    let wrapped = match wrap {
+        Wrap::Repeat => index.rem_euclid(size),
+        Wrap::Mirror => {
+            let period = index.rem_euclid(2 * size);
+            if period >= size { 2 * size - 1 - period } else { period }
+        },
+        Wrap::Clamp => index.clamp(0, size - 1),
+        Wrap::Border(_) => {
+            if index < 0 || index >= size {
+                return None;
+            }
+            index
+        }
+    };
+    Some(wrapped as usize)
+}
+
+fn add_weighted(total : &mut Texel, texel : &Texel, weight : f64) {
+    for (channel, value) in total.iter_mut().zip(texel.iter()) {
+        *channel += weight * value;
+    }
+}
+
+fn lerp_texel(a : &Texel, b : &Texel, t : f64) -> Texel {
+    let mut texel = [0.0; 4];
+    add_weighted(&mut texel, a, 1.0 - t);
+    add_weighted(&mut texel, b, t);
+    texel
+}
+
+fn catmull_rom(f : f64) -> [f64; 4] {
+    [
+        ((-0.5 * f + 1.0) * f - 0.5) * f,
+        (1.5 * f - 2.5) * f * f + 1.0,
+        ((-1.5 * f + 2.0) * f + 0.5) * f,
+        (0.5 * f - 0.5) * f * f
+    ]
+}
+
+struct Level {
+    width : usize,
+    height : usize,
+    texels : Vec<[f32; 4]>
+}
+
+impl Level {
+    fn from_image(image : &Rgba32FImage) -> Level {
+        Level {
+            width : image.width() as usize,
+            height : image.height() as usize,
+            texels : image.pixels().map(|pixel| pixel.0).collect()
+        }
+    }
+
+    // Box filtered to half the resolution, odd edges repeat their last texel
+    fn half(&self) -> Level {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity(width * height);
+        for j in 0..height {
+            for i in 0..width {
+                let mut sum = [0.0f32; 4];
+                for (di, dj) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let x = (2 * i + di).min(self.width - 1);
+                    let y = (2 * j + dj).min(self.height - 1);
+                    for (channel, value) in sum.iter_mut().zip(self.texels[y * self.width + x].iter()) {
+                        *channel += 0.25 * value;
+                    }
+                }
+                texels.push(sum);
+            }
+        }
+        Level { width, height, texels }
+    }
+
+    fn texel(&self, i : i64, j : i64, wrap : &Wrap) -> Texel {
+        match (wrap_index(i, self.width, wrap), wrap_index(j, self.height, wrap)) {
+            (Some(x), Some(y)) => self.texels[y * self.width + x].map(|value| value as f64),
+            _ => match wrap {
+                Wrap::Border(colour) => [colour.x(), colour.y(), colour.z(), 1.0],
+                _ => [0.0; 4]
+            }
+        }
+    }
+
+    // Position in texels with texel centres at half integers, v runs up the image
+    fn position(&self, u : f64, v : f64) -> (f64, f64) {
+        (u * self.width as f64 - 0.5, (1.0 - v) * self.height as f64 - 0.5)
+    }
+
+    fn sample(&self, u : f64, v : f64, filter : &Filter, wrap : &Wrap) -> Texel {
+        let (s, t) = self.position(u, v);
+        match filter {
+            Filter::Nearest => self.texel((s + 0.5).floor() as i64, (t + 0.5).floor() as i64, wrap),
+            Filter::Bilinear => {
+                let (i, j) = (s.floor(), t.floor());
+                let (fs, ft) = (s - i, t - j);
+                let (i, j) = (i as i64, j as i64);
+                let top = lerp_texel(&self.texel(i, j, wrap), &self.texel(i + 1, j, wrap), fs);
+                let bottom = lerp_texel(&self.texel(i, j + 1, wrap), &self.texel(i + 1, j + 1, wrap), fs);
+                lerp_texel(&top, &bottom, ft)
+            },
+            Filter::Bicubic => {
+                let (i, j) = (s.floor(), t.floor());
+                let (weights_s, weights_t) = (catmull_rom(s - i), catmull_rom(t - j));
+                let (i, j) = (i as i64, j as i64);
+                let mut total = [0.0; 4];
+                for (dj, weight_t) in weights_t.iter().enumerate() {
+                    for (di, weight_s) in weights_s.iter().enumerate() {
+                        let texel = self.texel(i + di as i64 - 1, j + dj as i64 - 1, wrap);
+                        add_weighted(&mut total, &texel, weight_s * weight_t);
+                    }
+                }
+                // Catmull-Rom overshoots at sharp edges
+                total.map(|value| value.max(0.0))
+            }
+        }
+    }
+
+    // Gaussian weighted average over the ellipse with the two axes in texture coordinates (Heckbert 1989)
+    fn ewa(&self, u : f64, v : f64, axis_0 : (f64, f64), axis_1 : (f64, f64), wrap : &Wrap) -> Texel {
+        let (s, t) = self.position(u, v);
+        let (width, height) = (self.width as f64, self.height as f64);
+        let (du_0, dv_0) = (axis_0.0 * width, -axis_0.1 * height);
+        let (du_1, dv_1) = (axis_1.0 * width, -axis_1.1 * height);
+        // Implicit ellipse a s^2 + b s t + c t^2 < 1, grown by a texel so it always covers one
+        let mut a = dv_0 * dv_0 + dv_1 * dv_1 + 1.0;
+        let mut b = -2.0 * (du_0 * dv_0 + du_1 * dv_1);
+        let mut c = du_0 * du_0 + du_1 * du_1 + 1.0;
+        let inv_f = 1.0 / (a * c - 0.25 * b * b);
+        a *= inv_f;
+        b *= inv_f;
+        c *= inv_f;
+        let determinant = 4.0 * a * c - b * b;
+        let s_extent = 2.0 * (determinant * c).sqrt() / determinant;
+        let t_extent = 2.0 * (determinant * a).sqrt() / determinant;
+        let mut total = [0.0; 4];
+        let mut weights = 0.0;
+        for j in (t - t_extent).ceil() as i64..=(t + t_extent).floor() as i64 {
+            let tt = j as f64 - t;
+            for i in (s - s_extent).ceil() as i64..=(s + s_extent).floor() as i64 {
+                let ss = i as f64 - s;
+                let r2 = a * ss * ss + b * ss * tt + c * tt * tt;
+                if r2 < 1.0 {
+                    let weight = (-EWA_ALPHA * r2).exp() - (-EWA_ALPHA).exp();
+                    add_weighted(&mut total, &self.texel(i, j, wrap), weight);
+                    weights += weight;
+                }
+            }
+        }
+        if weights <= 0.0 {
+            return self.sample(u, v, &Filter::Bilinear, wrap);
+        }
+        total.map(|value| value / weights)
+    }
+}
+
+// Image with its box filtered pyramid down to a single texel
+pub struct MipMap {
+    levels : Vec<Level>
+}
+
+impl MipMap {
+    pub fn new(image : &Rgba32FImage) -> MipMap {
+        let mut levels = vec![Level::from_image(image)];
+        while let Some(last) = levels.last() {
+            if last.width == 1 && last.height == 1 {
+                break;
+            }
+            let next = last.half();
+            levels.push(next);
+        }
+        MipMap { levels }
+    }
+
+    pub fn sample(&self, u : f64, v : f64, filter : &Filter, wrap : &Wrap) -> Texel {
+        self.levels[0].sample(u, v, filter, wrap)
+    }
+
+    // Continuous level where a texel is as wide as the given width in texture coordinates
+    fn level_for(&self, width : f64) -> f64 {
+        let resolution = self.levels[0].width.max(self.levels[0].height) as f64;
+        (width * resolution).max(1e-8).log2().clamp(0.0, (self.levels.len() - 1) as f64)
+    }
+
+    pub fn filtered(&self, u : f64, v : f64, footprint : &Footprint, filter : &Filter, wrap : &Wrap, mip_filter : &MipFilter) -> Texel {
+        let axis_x = (footprint.dudx, footprint.dvdx);
+        let axis_y = (footprint.dudy, footprint.dvdy);
+        match mip_filter {
+            MipFilter::Off => self.sample(u, v, filter, wrap),
+            MipFilter::Trilinear => {
+                let width = 2.0 * [axis_x.0, axis_x.1, axis_y.0, axis_y.1].iter()
+                    .fold(0.0, |width : f64, d| width.max(d.abs()));
+                let level = self.level_for(width);
+                let below = level.floor() as usize;
+                if below + 1 >= self.levels.len() {
+                    return self.levels[below].sample(u, v, filter, wrap);
+                }
+                lerp_texel(
+                    &self.levels[below].sample(u, v, filter, wrap),
+                    &self.levels[below + 1].sample(u, v, filter, wrap),
+                    level - below as f64
+                )
+            },
+            MipFilter::Ewa => {
+                let length = |axis : (f64, f64)| (axis.0 * axis.0 + axis.1 * axis.1).sqrt();
+                let (mut major, mut minor) = (axis_x, axis_y);
+                if length(minor) > length(major) {
+                    (major, minor) = (minor, major);
+                }
+                let (major_length, mut minor_length) = (length(major), length(minor));
+                if minor_length <= 0.0 {
+                    return self.sample(u, v, &Filter::Bilinear, wrap);
+                }
+                // Very thin ellipses would cover too many texels so widen them and blur a little
+                if minor_length * MAX_ANISOTROPY < major_length {
+                    let scale = major_length / (minor_length * MAX_ANISOTROPY);
+                    minor = (minor.0 * scale, minor.1 * scale);
+                    minor_length *= scale;
+                }
+                let level = self.level_for(minor_length);
+                let below = level.floor() as usize;
+                // The last level is one texel averaging the whole image. A footprint reaching it could
+                // cover any number of copies so it is used as it is instead of looping over them
+                if below + 1 >= self.levels.len() {
+                    return self.levels[below].texel(0, 0, wrap);
+                }
+                lerp_texel(
+                    &self.levels[below].ewa(u, v, major, minor, wrap),
+                    &self.levels[below + 1].ewa(u, v, major, minor, wrap),
+                    level - below as f64
+                )
+            }
+        }
+    }
+}
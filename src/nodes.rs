@@ -0,0 +1,357 @@
+use crate::colour::{attenuate, Colour};
+use crate::hittables::HitRecord;
+use crate::quaternion::Quaternion;
+use crate::textures::{Footprint, Node, Scalar, Texture};
+use crate::vec3::{Point3, Vec3};
+
+fn average(colour : &Colour) -> f64 {
+    (colour.x() + colour.y() + colour.z()) / 3.0
+}
+
+fn factor_of(factor : &Scalar, lookup : &dyn Fn(&dyn Texture) -> Colour) -> f64 {
+    match factor {
+        Scalar::Value(value) => *value,
+        Scalar::Texture(texture) => average(&lookup(*texture))
+    }
+}
+
+// Hue in turns, saturation and value
+fn rgb_to_hsv(colour : &Colour) -> (f64, f64, f64) {
+    let (r, g, b) = (colour.x(), colour.y(), colour.z());
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let saturation = if max > 0.0 { delta / max } else { 0.0 };
+    let hue = if delta <= 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.0) / 6.0
+    } else if max == g {
+        ((b - r) / delta + 2.0) / 6.0
+    } else {
+        ((r - g) / delta + 4.0) / 6.0
+    };
+    (hue, saturation, max)
+}
+
+fn hsv_to_rgb(hue : f64, saturation : f64, value : f64) -> Colour {
+    let sector = hue.rem_euclid(1.0) * 6.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x)
+    };
+    let min = value - chroma;
+    Colour::new(r + min, g + min, b + min)
+}
+
+// How the second texture is combined with the first, the factor fades between the first and the result
+#[derive(Copy, Clone)]
+pub enum Blend {
+    Mix,
+    Add,
+    Multiply
+}
+
+pub struct MixTexture<'tex> {
+    blend : Blend,
+    factor : Scalar<'tex>,
+    first : &'tex dyn Texture,
+    second : &'tex dyn Texture
+}
+
+impl<'tex> MixTexture<'tex> {
+    pub fn new(blend : Blend, factor : Scalar<'tex>, first : &'tex dyn Texture, second : &'tex dyn Texture) -> MixTexture<'tex> {
+        MixTexture {
+            blend,
+            factor,
+            first,
+            second
+        }
+    }
+}
+
+impl<'tex> Node for MixTexture<'tex> {
+    fn combine(&self, _u : f64, _v : f64, _point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        let factor = factor_of(&self.factor, lookup);
+        let first = lookup(self.first);
+        let second = lookup(self.second);
+        let blended = match self.blend {
+            Blend::Mix => second,
+            Blend::Add => first + second,
+            Blend::Multiply => attenuate(&first, &second)
+        };
+        (1.0 - factor) * first + factor * blended
+    }
+}
+
+// Maps the average of the input onto colours interpolated between stops, held at the ends
+pub struct RampTexture<'tex> {
+    input : &'tex dyn Texture,
+    stops : Vec<(f64, Colour)>
+}
+
+impl<'tex> RampTexture<'tex> {
+    pub fn new(input : &'tex dyn Texture, stops : &[(f64, Colour)]) -> RampTexture<'tex> {
+        let mut stops = stops.to_vec();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        RampTexture { input, stops }
+    }
+
+    fn ramp(&self, input : &Colour) -> Colour {
+        let position = average(input);
+        let Some(first) = self.stops.first() else {
+            return Colour::zero();
+        };
+        if position <= first.0 {
+            return first.1;
+        }
+        for pair in self.stops.windows(2) {
+            let ((start, from), (end, to)) = (pair[0], pair[1]);
+            if position <= end {
+                let t = if end > start { (position - start) / (end - start) } else { 1.0 };
+                return (1.0 - t) * from + t * to;
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
+}
+
+impl<'tex> Node for RampTexture<'tex> {
+    fn combine(&self, _u : f64, _v : f64, _point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        self.ramp(&lookup(self.input))
+    }
+}
+
+// Rotates the hue by degrees and scales the saturation and value
+pub struct HsvTexture<'tex> {
+    texture : &'tex dyn Texture,
+    hue_shift : f64,
+    saturation : f64,
+    value : f64
+}
+
+impl<'tex> HsvTexture<'tex> {
+    pub fn new(texture : &'tex dyn Texture, hue_shift : f64, saturation : f64, value : f64) -> HsvTexture<'tex> {
+        HsvTexture {
+            texture,
+            hue_shift,
+            saturation,
+            value
+        }
+    }
+
+    fn adjust(&self, colour : &Colour) -> Colour {
+        let (hue, saturation, value) = rgb_to_hsv(colour);
+        hsv_to_rgb(
+            hue + self.hue_shift / 360.0,
+            (saturation * self.saturation).clamp(0.0, 1.0),
+            value * self.value
+        )
+    }
+}
+
+impl<'tex> Node for HsvTexture<'tex> {
+    fn combine(&self, _u : f64, _v : f64, _point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        self.adjust(&lookup(self.texture))
+    }
+}
+
+// One minus each channel
+pub struct InvertTexture<'tex> {
+    texture : &'tex dyn Texture
+}
+
+impl<'tex> InvertTexture<'tex> {
+    pub fn new(texture : &'tex dyn Texture) -> InvertTexture<'tex> {
+        InvertTexture { texture }
+    }
+}
+
+fn invert(colour : &Colour) -> Colour {
+    Colour::new(1.0 - colour.x(), 1.0 - colour.y(), 1.0 - colour.z())
+}
+
+impl<'tex> Node for InvertTexture<'tex> {
+    fn combine(&self, _u : f64, _v : f64, _point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        invert(&lookup(self.texture))
+    }
+}
+
+// Each channel held between a minimum and maximum
+pub struct ClampTexture<'tex> {
+    texture : &'tex dyn Texture,
+    min : f64,
+    max : f64
+}
+
+impl<'tex> ClampTexture<'tex> {
+    pub fn new(texture : &'tex dyn Texture, min : f64, max : f64) -> ClampTexture<'tex> {
+        ClampTexture {
+            texture,
+            min,
+            max : max.max(min)
+        }
+    }
+
+    fn clamp(&self, colour : &Colour) -> Colour {
+        Colour::new(
+            colour.x().clamp(self.min, self.max),
+            colour.y().clamp(self.min, self.max),
+            colour.z().clamp(self.min, self.max)
+        )
+    }
+}
+
+impl<'tex> Node for ClampTexture<'tex> {
+    fn combine(&self, _u : f64, _v : f64, _point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        self.clamp(&lookup(self.texture))
+    }
+}
+
+enum Transform {
+    // Scale, rotation as cosine and sine, then offset of the texture coordinates
+    Uv((f64, f64), (f64, f64), (f64, f64)),
+    // Scale along each axis, rotation, then offset of the point
+    Object(Vec3, Quaternion, Vec3)
+}
+
+// Looks the texture up at transformed coordinates, scaling up repeats the pattern more often
+pub struct TransformTexture<'tex> {
+    texture : &'tex dyn Texture,
+    transform : Transform
+}
+
+impl<'tex> TransformTexture<'tex> {
+    // Angle in degrees
+    pub fn uv(texture : &'tex dyn Texture, scale : (f64, f64), angle : f64, offset : (f64, f64)) -> TransformTexture<'tex> {
+        let radians = angle.to_radians();
+        TransformTexture {
+            texture,
+            transform : Transform::Uv(scale, (radians.cos(), radians.sin()), offset)
+        }
+    }
+
+    pub fn object(texture : &'tex dyn Texture, scale : &Vec3, rotation : &Quaternion, offset : &Vec3) -> TransformTexture<'tex> {
+        TransformTexture {
+            texture,
+            transform : Transform::Object(*scale, rotation.unit(), *offset)
+        }
+    }
+
+    fn apply(&self, u : f64, v : f64, point : &Point3) -> (f64, f64, Point3) {
+        match &self.transform {
+            Transform::Uv(scale, (cos, sin), offset) => {
+                let (u, v) = (u * scale.0, v * scale.1);
+                (cos * u - sin * v + offset.0, sin * u + cos * v + offset.1, *point)
+            },
+            Transform::Object(..) => (u, v, self.move_point(point))
+        }
+    }
+
+    fn move_point(&self, point : &Point3) -> Point3 {
+        match &self.transform {
+            Transform::Uv(..) => *point,
+            Transform::Object(scale, rotation, offset) => {
+                let scaled = Point3::new(point.x() * scale.x(), point.y() * scale.y(), point.z() * scale.z());
+                rotation.rotate(&scaled) + offset
+            }
+        }
+    }
+
+    // Normals scale by the inverse of the scale to stay perpendicular to the surface
+    fn move_normal(&self, normal : &Vec3) -> Vec3 {
+        match &self.transform {
+            Transform::Uv(..) => *normal,
+            Transform::Object(scale, rotation, _) => {
+                let scaled = Vec3::new(normal.x() / scale.x(), normal.y() / scale.y(), normal.z() / scale.z());
+                rotation.rotate(&scaled).unit()
+            }
+        }
+    }
+
+    // The footprint goes through the linear part of the texture coordinate transform
+    fn move_footprint(&self, footprint : &Footprint) -> Footprint {
+        match &self.transform {
+            Transform::Uv(scale, (cos, sin), _) => {
+                let change = |du : f64, dv : f64| {
+                    let (du, dv) = (du * scale.0, dv * scale.1);
+                    (cos * du - sin * dv, sin * du + cos * dv)
+                };
+                let (dudx, dvdx) = change(footprint.dudx, footprint.dvdx);
+                let (dudy, dvdy) = change(footprint.dudy, footprint.dvdy);
+                Footprint { dudx, dvdx, dudy, dvdy }
+            },
+            Transform::Object(..) => *footprint
+        }
+    }
+
+    // Object space moves too so projections underneath follow the transform
+    fn move_hit<'a>(&self, hit_record : &HitRecord<'a>) -> HitRecord<'a> {
+        let (u, v, point) = self.apply(hit_record.u, hit_record.v, &hit_record.point);
+        let (object_dpdu, object_dpdv) = self.move_tangents(&hit_record.object_dpdu, &hit_record.object_dpdv);
+        HitRecord {
+            u,
+            v,
+            point,
+            object_point : self.move_point(&hit_record.object_point),
+            object_normal : self.move_normal(&hit_record.object_normal),
+            object_dpdu,
+            object_dpdv,
+            footprint : hit_record.footprint.map(|footprint| self.move_footprint(&footprint)),
+            ..*hit_record
+        }
+    }
+
+    // Tangents along the moved coordinates, the uv transform is undone so that the tangents times the
+    // moved footprint still give the same step across the surface
+    fn move_tangents(&self, dpdu : &Vec3, dpdv : &Vec3) -> (Vec3, Vec3) {
+        match &self.transform {
+            Transform::Uv(scale, (cos, sin), _) => {
+                let (along_u, along_v) = (dpdu / scale.0, dpdv / scale.1);
+                (*cos * along_u - *sin * along_v, *sin * along_u + *cos * along_v)
+            },
+            Transform::Object(scale, rotation, _) => {
+                let stretch = |tangent : &Vec3| {
+                    let scaled = Vec3::new(tangent.x() * scale.x(), tangent.y() * scale.y(), tangent.z() * scale.z());
+                    rotation.rotate(&scaled)
+                };
+                (stretch(dpdu), stretch(dpdv))
+            }
+        }
+    }
+}
+
+impl<'tex> Texture for TransformTexture<'tex> {
+    fn value(&self, u : f64, v : f64, point : &Point3) -> Colour {
+        self.value_at(u, v, point, 0.0)
+    }
+
+    fn value_at(&self, u : f64, v : f64, point : &Point3, time : f64) -> Colour {
+        let (u, v, point) = self.apply(u, v, point);
+        self.texture.value_at(u, v, &point, time)
+    }
+
+    fn filtered(&self, u : f64, v : f64, point : &Point3, time : f64, footprint : &Footprint) -> Colour {
+        let (u, v, point) = self.apply(u, v, point);
+        self.texture.filtered(u, v, &point, time, &self.move_footprint(footprint))
+    }
+
+    fn value_at_hit(&self, hit_record : &HitRecord) -> Colour {
+        self.texture.value_at_hit(&self.move_hit(hit_record))
+    }
+
+    fn alpha(&self, u : f64, v : f64, point : &Point3) -> f64 {
+        let (u, v, point) = self.apply(u, v, point);
+        self.texture.alpha(u, v, &point)
+    }
+
+    fn alpha_at_hit(&self, hit_record : &HitRecord) -> f64 {
+        self.texture.alpha_at_hit(&self.move_hit(hit_record))
+    }
+}
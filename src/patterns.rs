@@ -0,0 +1,250 @@
+use rand::Rng;
+use crate::colour::Colour;
+use crate::perlin::Perlin;
+use crate::textures::{Node, Texture};
+use crate::vec3::{dot, Point3, Vec3};
+
+fn mix(a : &Colour, b : &Colour, t : f64) -> Colour {
+    (1.0 - t) * a + t * b
+}
+
+// Distance from the nearest whole number, half way between two is 0.5
+fn line_distance(x : f64) -> f64 {
+    let fract = x - x.floor();
+    fract.min(1.0 - fract)
+}
+
+// Growth rings around the y axis, each ring fades from the light wood to the dark wood
+// before the next starts. The rings are pushed around by noise stretched along the grain
+pub struct WoodTexture<'tex> {
+    light : &'tex dyn Texture,
+    dark : &'tex dyn Texture,
+    ring_spacing : f64,
+    grain : f64,
+    noise : Perlin
+}
+
+impl<'tex> WoodTexture<'tex> {
+    pub fn new<R>(rng : &mut R, ring_spacing : f64, light : &'tex dyn Texture, dark : &'tex dyn Texture) -> WoodTexture<'tex>
+    where R : Rng
+    {
+        WoodTexture {
+            light,
+            dark,
+            ring_spacing,
+            grain : 0.5,
+            noise : Perlin::new(rng)
+        }
+    }
+
+    // How far in ring widths the noise can move a ring, zero for perfect circles
+    pub fn set_grain(&mut self, grain : f64) {
+        self.grain = grain;
+    }
+
+    fn rings(&self, point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        let along_grain = Point3::new(point.x(), 0.1 * point.y(), point.z()) / self.ring_spacing;
+        let radius = (point.x() * point.x() + point.z() * point.z()).sqrt() / self.ring_spacing;
+        let rings = radius + self.grain * self.noise.fbm(&along_grain, 4, 2.0, 0.5);
+        let t = (rings - rings.floor()).powi(3);
+        mix(&lookup(self.light), &lookup(self.dark), t)
+    }
+}
+
+impl<'tex> Node for WoodTexture<'tex> {
+    fn combine(&self, _u : f64, _v : f64, point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        self.rings(point, lookup)
+    }
+}
+
+// Rows of bricks in texture coordinates separated by mortar, each row shifted by half a brick by default.
+// A row offset of zero lines the bricks up into tiles
+pub struct BrickTexture<'tex> {
+    brick : &'tex dyn Texture,
+    mortar : &'tex dyn Texture,
+    width : f64,
+    height : f64,
+    mortar_width : f64,
+    row_offset : f64
+}
+
+impl<'tex> BrickTexture<'tex> {
+    pub fn new(width : f64, height : f64, mortar_width : f64, brick : &'tex dyn Texture, mortar : &'tex dyn Texture) -> BrickTexture<'tex> {
+        BrickTexture {
+            brick,
+            mortar,
+            width,
+            height,
+            mortar_width,
+            row_offset : 0.5
+        }
+    }
+
+    // Fraction of a brick each row is shifted along from the one below
+    pub fn set_row_offset(&mut self, row_offset : f64) {
+        self.row_offset = row_offset;
+    }
+
+    fn choose(&self, u : f64, v : f64) -> &'tex dyn Texture {
+        let row = (v / self.height).floor();
+        let along = u / self.width + row * self.row_offset;
+        let up = v / self.height;
+        // Mortar is split evenly between the bricks either side of a joint
+        let in_mortar = line_distance(along) * self.width < 0.5 * self.mortar_width
+            || line_distance(up) * self.height < 0.5 * self.mortar_width;
+        if in_mortar { self.mortar } else { self.brick }
+    }
+}
+
+impl<'tex> Node for BrickTexture<'tex> {
+    fn combine(&self, u : f64, v : f64, _point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        lookup(self.choose(u, v))
+    }
+}
+
+enum Shape {
+    // Start point and direction scaled so the end is one along it
+    Linear(Point3, Vec3),
+    // Centre and radius
+    Radial(Point3, f64)
+}
+
+// Blends from one texture to another across space, clamped to the first and last beyond the ends
+pub struct GradientTexture<'tex> {
+    shape : Shape,
+    from : &'tex dyn Texture,
+    to : &'tex dyn Texture
+}
+
+impl<'tex> GradientTexture<'tex> {
+    pub fn linear(start : &Point3, end : &Point3, from : &'tex dyn Texture, to : &'tex dyn Texture) -> GradientTexture<'tex> {
+        let direction = end - start;
+        let length_squared = direction.length_squared();
+        let direction = if length_squared > 0.0 { direction / length_squared } else { Vec3::zero() };
+        GradientTexture {
+            shape : Shape::Linear(*start, direction),
+            from,
+            to
+        }
+    }
+
+    // A radius of zero or less is all the last texture
+    pub fn radial(centre : &Point3, radius : f64, from : &'tex dyn Texture, to : &'tex dyn Texture) -> GradientTexture<'tex> {
+        GradientTexture {
+            shape : Shape::Radial(*centre, radius),
+            from,
+            to
+        }
+    }
+
+    fn blend(&self, point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        let t = match &self.shape {
+            Shape::Linear(start, direction) => dot(&(point - start), direction),
+            Shape::Radial(centre, radius) => {
+                if *radius > 0.0 { (point - centre).length() / radius } else { 1.0 }
+            }
+        }.clamp(0.0, 1.0);
+        mix(&lookup(self.from), &lookup(self.to), t)
+    }
+}
+
+impl<'tex> Node for GradientTexture<'tex> {
+    fn combine(&self, _u : f64, _v : f64, point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        self.blend(point, lookup)
+    }
+}
+
+// Checker over texture coordinates with the given number of squares across u and v
+pub struct UvChecker<'tex> {
+    columns : f64,
+    rows : f64,
+    even : &'tex dyn Texture,
+    odd : &'tex dyn Texture
+}
+
+impl<'tex> UvChecker<'tex> {
+    pub fn new(columns : f64, rows : f64, even : &'tex dyn Texture, odd : &'tex dyn Texture) -> UvChecker<'tex> {
+        UvChecker {
+            columns,
+            rows,
+            even,
+            odd
+        }
+    }
+
+    fn choose(&self, u : f64, v : f64) -> &'tex dyn Texture {
+        let i = (u * self.columns).floor() as i64;
+        let j = (v * self.rows).floor() as i64;
+        if (i + j).rem_euclid(2) == 0 { self.even } else { self.odd }
+    }
+}
+
+impl<'tex> Node for UvChecker<'tex> {
+    fn combine(&self, u : f64, v : f64, _point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        lookup(self.choose(u, v))
+    }
+}
+
+// Lines between the cells of a grid over texture coordinates, the line width is a fraction of a cell
+pub struct UvGrid<'tex> {
+    columns : f64,
+    rows : f64,
+    line_width : f64,
+    line : &'tex dyn Texture,
+    fill : &'tex dyn Texture
+}
+
+impl<'tex> UvGrid<'tex> {
+    pub fn new(columns : f64, rows : f64, line_width : f64, line : &'tex dyn Texture, fill : &'tex dyn Texture) -> UvGrid<'tex> {
+        UvGrid {
+            columns,
+            rows,
+            line_width,
+            line,
+            fill
+        }
+    }
+
+    fn choose(&self, u : f64, v : f64) -> &'tex dyn Texture {
+        let half_width = 0.5 * self.line_width;
+        if line_distance(u * self.columns) < half_width || line_distance(v * self.rows) < half_width {
+            self.line
+        } else {
+            self.fill
+        }
+    }
+}
+
+impl<'tex> Node for UvGrid<'tex> {
+    fn combine(&self, u : f64, v : f64, _point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        lookup(self.choose(u, v))
+    }
+}
+
+// For checking texture coordinates, red rises along u and green along v so flips and seams show.
+// Alternate cells are darker with white lines between them
+pub struct UvTestPattern {
+    cells : f64
+}
+
+impl UvTestPattern {
+    pub fn new(cells : f64) -> UvTestPattern {
+        UvTestPattern { cells }
+    }
+}
+
+impl Texture for UvTestPattern {
+    fn value(&self, u : f64, v : f64, _point : &Point3) -> Colour {
+        let (s, t) = (u * self.cells, v * self.cells);
+        if line_distance(s) < 0.03 || line_distance(t) < 0.03 {
+            return Colour::new(1.0, 1.0, 1.0);
+        }
+        let (fract_u, fract_v) = (u - u.floor(), v - v.floor());
+        let colour = Colour::new(fract_u, fract_v, 1.0 - 0.5 * (fract_u + fract_v));
+        if (s.floor() as i64 + t.floor() as i64).rem_euclid(2) == 0 {
+            colour
+        } else {
+            0.5 * colour
+        }
+    }
+}
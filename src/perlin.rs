@@ -1,16 +1,22 @@
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use crate::vec3::{dot, random_unit, Point3, Vec3};
 
-const POINT_COUNT : usize = 256;
+pub(crate) const POINT_COUNT : usize = 256;
 pub struct Perlin {
     vectors : [Vec3; POINT_COUNT],
+    // Gradients for noise varying in time as well
+    vectors_4d : [[f64; 4]; POINT_COUNT],
     x_permute : [u16; POINT_COUNT],
     y_permute : [u16; POINT_COUNT],
-    z_permute : [u16; POINT_COUNT]
+    z_permute : [u16; POINT_COUNT],
+    w_permute : [u16; POINT_COUNT],
+    // Lattice cells before the noise repeats along each axis
+    period : i64
 }
 
-fn make_permute<R>(rng : &mut R) -> [u16; POINT_COUNT]
+pub(crate) fn make_permute<R>(rng : &mut R) -> [u16; POINT_COUNT]
 where R : Rng {
     let mut permute : [u16; POINT_COUNT] = [0; POINT_COUNT];
     permute.iter_mut().enumerate().for_each(
@@ -20,11 +26,55 @@ where R : Rng {
     permute
 }
 
-fn split_index(val : f64) -> (usize, usize, f64) {
-    let fint = val.floor();
-    let rem = val - fint;
-    let int = fint as i64;
-    ((int as usize) & (POINT_COUNT - 1), ((int+1) as usize) & (POINT_COUNT - 1), rem)
+fn random_unit_4d<R>(rng : &mut R) -> [f64; 4]
+where R : Rng {
+    loop {
+        let vector : [f64; 4] = [rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)];
+        let length_squared : f64 = vector.iter().map(|x| x * x).sum();
+        if length_squared > 1e-8 && length_squared <= 1.0 {
+            let length = length_squared.sqrt();
+            return vector.map(|x| x / length);
+        }
+    }
+}
+
+fn smooth(t : f64) -> f64 {
+    t*t*(3.0-2.0*t)
+}
+
+// Octaves rise in frequency by the lacunarity and fall in amplitude by the gain, noise is given the frequency
+fn fbm_sum<F>(noise : F, octaves : usize, lacunarity : f64, gain : f64) -> f64
+where F : Fn(f64) -> f64 {
+    let mut frequency = 1.0;
+    let mut amplitude = 1.0;
+    let mut total = 0.0;
+    let mut val = 0.0;
+    for _ in 0..octaves {
+        val += amplitude * noise(frequency);
+        total += amplitude;
+        amplitude *= gain;
+        frequency *= lacunarity;
+    }
+    if total > 0.0 { val / total } else { 0.0 }
+}
+
+fn ridged_sum<F>(noise : F, octaves : usize, lacunarity : f64, gain : f64) -> f64
+where F : Fn(f64) -> f64 {
+    let mut frequency = 1.0;
+    let mut amplitude = 1.0;
+    let mut weight = 1.0;
+    let mut total = 0.0;
+    let mut val = 0.0;
+    for _ in 0..octaves {
+        let ridge = 1.0 - noise(frequency).abs();
+        let signal = ridge * ridge * weight;
+        val += amplitude * signal;
+        total += amplitude;
+        weight = (2.0 * signal).clamp(0.0, 1.0);
+        amplitude *= gain;
+        frequency *= lacunarity;
+    }
+    if total > 0.0 { val / total } else { 0.0 }
 }
 
 fn perlin_interp(verts : &[Vec3; 8], x : f64, y : f64, z : f64) -> f64 {
@@ -53,22 +103,49 @@ impl Perlin {
         let x_permute = make_permute(rng);
         let y_permute = make_permute(rng);
         let z_permute = make_permute(rng);
+        let mut vectors_4d : [[f64; 4]; POINT_COUNT] = [[0.0; 4]; POINT_COUNT];
+        vectors_4d.iter_mut().for_each(
+            |val| *val = random_unit_4d(rng)
+        );
+        let w_permute = make_permute(rng);
 
         Perlin {
             vectors,
+            vectors_4d,
             x_permute,
             y_permute,
-            z_permute
+            z_permute,
+            w_permute,
+            period : POINT_COUNT as i64
         }
     }
 
+    // The same noise on every run for the same seed
+    pub fn seeded(seed : u64) -> Perlin {
+        Perlin::new(&mut StdRng::seed_from_u64(seed))
+    }
+
+    // Repeat every period units along each axis so the noise tiles, from 1 up to the default of 256
+    // as the lattice has no more gradients. Octaves only stay tiling when the lacunarity is a whole number
+    pub fn set_period(&mut self, period : usize) {
+        self.period = period.clamp(1, POINT_COUNT) as i64;
+    }
+
+    fn split_index(&self, val : f64) -> (usize, usize, f64) {
+        let fint = val.floor();
+        let rem = val - fint;
+        let int = fint as i64;
+        let wrap = |i : i64| i.rem_euclid(self.period) as usize;
+        (wrap(int), wrap(int + 1), rem)
+    }
+
     pub fn noise(&self, point : &Point3) -> f64 {
-        let (x_min, x_max, x_rem) = split_index(point.x());
-        let (y_min, y_max, y_rem) = split_index(point.y());
-        let (z_min, z_max, z_rem) = split_index(point.z());
-        let u = x_rem*x_rem*(3.0-2.0*x_rem);
-        let v = y_rem*y_rem*(3.0-2.0*y_rem);
-        let w = z_rem*z_rem*(3.0-2.0*z_rem);
+        let (x_min, x_max, x_rem) = self.split_index(point.x());
+        let (y_min, y_max, y_rem) = self.split_index(point.y());
+        let (z_min, z_max, z_rem) = self.split_index(point.z());
+        let u = smooth(x_rem);
+        let v = smooth(y_rem);
+        let w = smooth(z_rem);
         let vecs = [
             self.vector(x_max, y_max, z_max),
             self.vector(x_max, y_max, z_min),
@@ -82,6 +159,38 @@ impl Perlin {
         perlin_interp(&vecs, u, v, w)
     }
 
+    // Noise over space and time, slices at nearby times blend smoothly into each other
+    pub fn noise_4d(&self, point : &Point3, time : f64) -> f64 {
+        let splits = [
+            self.split_index(point.x()),
+            self.split_index(point.y()),
+            self.split_index(point.z()),
+            self.split_index(time)
+        ];
+        let mut val = 0.0;
+        // Corners of the hypercube, bit n set for the upper lattice point along axis n
+        for corner in 0..16 {
+            let mut weight = 1.0;
+            let mut indices = [0; 4];
+            let mut offset = [0.0; 4];
+            for (axis, (min, max, rem)) in splits.iter().enumerate() {
+                if (corner >> axis) & 1 == 1 {
+                    weight *= smooth(*rem);
+                    indices[axis] = *max;
+                    offset[axis] = rem - 1.0;
+                } else {
+                    weight *= 1.0 - smooth(*rem);
+                    indices[axis] = *min;
+                    offset[axis] = *rem;
+                }
+            }
+            let index = self.x_permute[indices[0]] ^ self.y_permute[indices[1]] ^ self.z_permute[indices[2]] ^ self.w_permute[indices[3]];
+            let gradient = self.vectors_4d[index as usize];
+            val += weight * gradient.iter().zip(offset.iter()).map(|(g, o)| g * o).sum::<f64>();
+        }
+        val
+    }
+
     pub fn turbulence(&self, point : &Point3, depth : usize) -> f64 {
         let mut curr_point = *point;
         let mut weight = 0.5;
@@ -94,8 +203,29 @@ impl Perlin {
         val.abs()
     }
 
+    // Fractal Brownian motion, octaves rise in frequency by the lacunarity and fall in amplitude by the gain.
+    // Normalised by the total amplitude so it stays roughly within [-1, 1]
+    pub fn fbm(&self, point : &Point3, octaves : usize, lacunarity : f64, gain : f64) -> f64 {
+        fbm_sum(|frequency| self.noise(&(frequency * point)), octaves, lacunarity, gain)
+    }
+
+    // Fbm evolving in time, finer octaves change faster
+    pub fn fbm_4d(&self, point : &Point3, time : f64, octaves : usize, lacunarity : f64, gain : f64) -> f64 {
+        fbm_sum(|frequency| self.noise_4d(&(frequency * point), frequency * time), octaves, lacunarity, gain)
+    }
+
+    // Ridged multifractal (Musgrave), folded noise makes sharp crests and each octave is weighted
+    // by the one before so detail gathers on the ridges. Within [0, 1]
+    pub fn ridged(&self, point : &Point3, octaves : usize, lacunarity : f64, gain : f64) -> f64 {
+        ridged_sum(|frequency| self.noise(&(frequency * point)), octaves, lacunarity, gain)
+    }
+
+    pub fn ridged_4d(&self, point : &Point3, time : f64, octaves : usize, lacunarity : f64, gain : f64) -> f64 {
+        ridged_sum(|frequency| self.noise_4d(&(frequency * point), frequency * time), octaves, lacunarity, gain)
+    }
+
     fn vector(&self, i : usize, j : usize, k : usize) -> Vec3 {
         let index = self.x_permute[i] ^ self.y_permute[j] ^ self.z_permute[k];
         self.vectors[index as usize]
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,219 @@
+use std::f64::consts::PI;
+use rand::Rng;
+use rand::rngs::ThreadRng;
+use crate::colour::Colour;
+use crate::hittables::HitRecord;
+use crate::materials::Material;
+use crate::microfacet::{evaluate_dielectric, sample_dielectric, Ggx};
+use crate::ray::Ray;
+use crate::textures::{Scalar, Texture};
+use crate::vec3::{dot, random_unit, reflect, OrthonormalBasis, UnitVec3, Vec3};
+
+// Rougher than this so every lobe can be light sampled
+const MIN_ROUGHNESS : f64 = 0.05;
+// Coat is a fixed index of 1.5 layer
+const CLEARCOAT_F0 : f64 = 0.04;
+
+fn schlick_weight(cos_th : f64) -> f64 {
+    (1.0 - cos_th).clamp(0.0, 1.0).powi(5)
+}
+
+fn lerp(a : &Colour, b : &Colour, t : f64) -> Colour {
+    (1.0 - t) * a + t * b
+}
+
+// Disney style uber material (Burley 2012 and 2015), parameters are in [0, 1] except the index
+pub struct Principled<'tex> {
+    base_colour : &'tex dyn Texture,
+    metallic : Scalar<'tex>,
+    roughness : Scalar<'tex>,
+    specular : Scalar<'tex>,
+    specular_tint : Scalar<'tex>,
+    sheen : Scalar<'tex>,
+    clearcoat : Scalar<'tex>,
+    clearcoat_roughness : Scalar<'tex>,
+    transmission : Scalar<'tex>,
+    refraction_index : Scalar<'tex>
+}
+
+impl<'tex> Principled<'tex> {
+    pub fn new(base_colour : &'tex dyn Texture, metallic : Scalar<'tex>, roughness : Scalar<'tex>) -> Principled<'tex> {
+        Principled {
+            base_colour,
+            metallic,
+            roughness,
+            specular : Scalar::Value(0.5),
+            specular_tint : Scalar::Value(0.0),
+            sheen : Scalar::Value(0.0),
+            clearcoat : Scalar::Value(0.0),
+            clearcoat_roughness : Scalar::Value(0.1),
+            transmission : Scalar::Value(0.0),
+            refraction_index : Scalar::Value(1.5)
+        }
+    }
+
+    // Specular of 0.5 is a reflectance of 4% like an index of 1.5
+    pub fn set_specular(&mut self, specular : Scalar<'tex>, specular_tint : Scalar<'tex>) {
+        self.specular = specular;
+        self.specular_tint = specular_tint;
+    }
+
+    pub fn set_sheen(&mut self, sheen : Scalar<'tex>) {
+        self.sheen = sheen;
+    }
+
+    pub fn set_clearcoat(&mut self, clearcoat : Scalar<'tex>, roughness : Scalar<'tex>) {
+        self.clearcoat = clearcoat;
+        self.clearcoat_roughness = roughness;
+    }
+
+    pub fn set_transmission(&mut self, transmission : Scalar<'tex>, refraction_index : Scalar<'tex>) {
+        self.transmission = transmission;
+        self.refraction_index = refraction_index;
+    }
+
+    // Look up the parameters at the hit
+    fn lobes(&self, hit_record : &HitRecord) -> Lobes {
+        let base = hit_record.texture_value(self.base_colour);
+        let metallic = self.metallic.value_at_hit(hit_record).clamp(0.0, 1.0);
+        let roughness = self.roughness.value_at_hit(hit_record).clamp(MIN_ROUGHNESS, 1.0);
+        let transmission = (1.0 - metallic) * self.transmission.value_at_hit(hit_record).clamp(0.0, 1.0);
+        let dielectric = (1.0 - metallic) * (1.0 - transmission);
+        let clearcoat = 0.25 * self.clearcoat.value_at_hit(hit_record).clamp(0.0, 1.0);
+        let clearcoat_roughness = self.clearcoat_roughness.value_at_hit(hit_record).clamp(MIN_ROUGHNESS, 1.0);
+        // Hue of the base colour without its brightness
+        let luminance = 0.3 * base.x() + 0.6 * base.y() + 0.1 * base.z();
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let tint = if luminance > 0.0 { base / luminance } else { white };
+        let specular = 0.08 * self.specular.value_at_hit(hit_record)
+            * lerp(&white, &tint, self.specular_tint.value_at_hit(hit_record));
+        let refraction_index = self.refraction_index.value_at_hit(hit_record);
+        let weights = [dielectric, (1.0 - transmission) * (0.25 + 0.75 * metallic), clearcoat, transmission];
+        let total : f64 = weights.iter().sum();
+        Lobes {
+            diffuse : dielectric * base,
+            sheen : dielectric * self.sheen.value_at_hit(hit_record) * lerp(&white, &tint, 0.5),
+            roughness,
+            specular_f0 : lerp(&specular, &base, metallic),
+            specular_weight : 1.0 - transmission,
+            specular_distribution : Ggx::new(roughness, roughness),
+            clearcoat,
+            clearcoat_distribution : Ggx::new(clearcoat_roughness, clearcoat_roughness),
+            transmission,
+            transmission_tint : Colour::new(base.x().sqrt(), base.y().sqrt(), base.z().sqrt()),
+            eta : if hit_record.front_face { refraction_index } else { 1.0 / refraction_index },
+            probabilities : weights.map(|weight| weight / total)
+        }
+    }
+}
+
+// Principled parameters at a point, each lobe is chosen for sampling by its probability
+struct Lobes {
+    diffuse : Colour,
+    sheen : Colour,
+    roughness : f64,
+    specular_f0 : Colour,
+    specular_weight : f64,
+    specular_distribution : Ggx,
+    clearcoat : f64,
+    clearcoat_distribution : Ggx,
+    transmission : f64,
+    // Applied entering and leaving so a whole object is tinted by the base colour
+    transmission_tint : Colour,
+    eta : f64,
+    probabilities : [f64; 4]
+}
+
+impl Lobes {
+    fn sample<R>(&self, rng : &mut R, wo : &UnitVec3) -> Option<UnitVec3>
+    where R : Rng
+    {
+        let choice = rng.gen::<f64>();
+        let [diffuse, specular, clearcoat, _] = self.probabilities;
+        let wi = if choice < diffuse {
+            Vec3::new(0.0, 0.0, 1.0) + random_unit(rng)
+        } else if choice < diffuse + specular {
+            reflect(&-wo, &self.specular_distribution.sample_visible_normal(rng, wo))
+        } else if choice < diffuse + specular + clearcoat {
+            reflect(&-wo, &self.clearcoat_distribution.sample_visible_normal(rng, wo))
+        } else {
+            sample_dielectric(rng, &self.specular_distribution, self.eta, wo)?.1
+        };
+        if wi.length_squared() <= 0.0 {
+            return None;
+        }
+        Some(wi.unit())
+    }
+
+    // Scattering times the cosine and the pdf of sample choosing wi
+    fn evaluate(&self, wo : &UnitVec3, wi : &UnitVec3) -> (Colour, f64) {
+        if wo.z() <= 0.0 || wi.z() == 0.0 {
+            return (Colour::zero(), 0.0);
+        }
+        let mut value = Colour::zero();
+        let mut pdf = 0.0;
+        if wi.z() > 0.0 {
+            let half = wo + wi;
+            if half.length_squared() <= 0.0 {
+                return (Colour::zero(), 0.0);
+            }
+            let wm = half.unit();
+            let cos_d = dot(wi, &wm);
+            // Burley diffuse with retro-reflection at grazing angles plus sheen
+            let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+            let retro = (1.0 + (fd90 - 1.0) * schlick_weight(wi.z())) * (1.0 + (fd90 - 1.0) * schlick_weight(wo.z()));
+            value += (retro / PI * self.diffuse + schlick_weight(cos_d) * self.sheen) * wi.z();
+            pdf += self.probabilities[0] * wi.z() / PI;
+            // Specular reflection, tinted for metals
+            let white = Colour::new(1.0, 1.0, 1.0);
+            let fresnel = lerp(&self.specular_f0, &white, schlick_weight(cos_d));
+            let (specular, specular_pdf) = reflection(&self.specular_distribution, wo, wi, &wm);
+            value += self.specular_weight * specular * fresnel;
+            pdf += self.probabilities[1] * specular_pdf;
+            // Clearcoat on top
+            let fresnel = CLEARCOAT_F0 + (1.0 - CLEARCOAT_F0) * schlick_weight(cos_d);
+            let (coat, coat_pdf) = reflection(&self.clearcoat_distribution, wo, wi, &wm);
+            value += Colour::new(1.0, 1.0, 1.0) * (self.clearcoat * fresnel * coat);
+            pdf += self.probabilities[2] * coat_pdf;
+        }
+        if self.transmission > 0.0 {
+            let (transmitted, transmission_pdf) = evaluate_dielectric(&self.specular_distribution, self.eta, wo, wi);
+            let tint = if wi.z() > 0.0 { Colour::new(1.0, 1.0, 1.0) } else { self.transmission_tint };
+            value += (self.transmission * transmitted) * tint;
+            pdf += self.probabilities[3] * transmission_pdf;
+        }
+        (value, pdf)
+    }
+}
+
+// GGX reflection without Fresnel times the cosine and its visible normal sampling pdf
+fn reflection(distribution : &Ggx, wo : &UnitVec3, wi : &UnitVec3, wm : &UnitVec3) -> (f64, f64) {
+    let cos_om = dot(wo, wm);
+    if cos_om <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let d = distribution.distribution(wm);
+    let g = distribution.masking_shadowing(wo, wi);
+    (d * g / (4.0 * wo.z()), distribution.visible_pdf(wo, wm) / (4.0 * cos_om))
+}
+
+impl<'tex> Material for Principled<'tex> {
+    fn scatter(&self, rng : &mut ThreadRng, ray_in : &Ray, hit_record : &HitRecord) -> Option<(Colour, Ray)> {
+        let frame = OrthonormalBasis::new(&hit_record.normal);
+        let wo = frame.to_local(&-ray_in.direction.unit());
+        let lobes = self.lobes(hit_record);
+        let wi = lobes.sample(rng, &wo)?;
+        let (value, pdf) = lobes.evaluate(&wo, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some((value / pdf, Ray::new(&hit_record.point, &frame.to_world(&wi), ray_in.time)))
+    }
+
+    fn evaluate(&self, ray_in : &Ray, hit_record : &HitRecord, direction : &Vec3) -> Option<(Colour, f64)> {
+        let frame = OrthonormalBasis::new(&hit_record.normal);
+        let wo = frame.to_local(&-ray_in.direction.unit());
+        let wi = frame.to_local(&direction.unit());
+        Some(self.lobes(hit_record).evaluate(&wo, &wi))
+    }
+}
@@ -0,0 +1,174 @@
+use std::f64::consts::PI;
+use std::ops;
+use crate::colour::Colour;
+use crate::hittables::HitRecord;
+use crate::textures::{Footprint, Texture};
+use crate::vec3::{axes3, Axis3, Point3, Vec3};
+
+// The two axes across a plane facing along an axis, chosen so images are upright on the sides
+fn across(axis : Axis3) -> (Axis3, Axis3) {
+    match axis {
+        Axis3::X => (Axis3::Z, Axis3::Y),
+        Axis3::Y => (Axis3::X, Axis3::Z),
+        Axis3::Z => (Axis3::X, Axis3::Y)
+    }
+}
+
+fn planar(axis : Axis3, local : &Vec3) -> (f64, f64) {
+    let (u_axis, v_axis) = across(axis);
+    (local.coord(u_axis), local.coord(v_axis))
+}
+
+fn spherical(axis : Axis3, local : &Vec3) -> (f64, f64) {
+    let (across_u, across_v) = across(axis);
+    let radius = local.length();
+    let height = if radius > 0.0 { local.coord(axis) / radius } else { 0.0 };
+    let angle = local.coord(across_v).atan2(local.coord(across_u));
+    (0.5 + angle / (2.0 * PI), 1.0 - height.clamp(-1.0, 1.0).acos() / PI)
+}
+
+fn cylindrical(axis : Axis3, local : &Vec3) -> (f64, f64) {
+    let (across_u, across_v) = across(axis);
+    let angle = local.coord(across_v).atan2(local.coord(across_u));
+    (0.5 + angle / (2.0 * PI), local.coord(axis))
+}
+
+// Coordinates the mapping gives the local point. With differentials the change across a pixel in object
+// space is pushed through the mapping too so image textures underneath can filter. Wrapped mappings jump
+// by one at the seam, which is taken out of the change in u
+fn coordinates(map : &dyn Fn(&Vec3) -> (f64, f64), wraps : bool, local : &Vec3, differentials : Option<(Vec3, Vec3)>) -> (f64, f64, Option<Footprint>) {
+    let (u, v) = map(local);
+    let footprint = differentials.map(|(dpdx, dpdy)| {
+        let change = |dp : &Vec3| {
+            let (moved_u, moved_v) = map(&(local + dp));
+            let du = moved_u - u;
+            (if wraps { du - du.round() } else { du }, moved_v - v)
+        };
+        let (dudx, dvdx) = change(&dpdx);
+        let (dudy, dvdy) = change(&dpdy);
+        Footprint { dudx, dvdx, dudy, dvdy }
+    });
+    (u, v, footprint)
+}
+
+enum Mapping {
+    // Straight along an axis, one unit of the object across is one of the texture
+    Planar(Axis3),
+    // Longitude and latitude around an axis
+    Spherical(Axis3),
+    // Angle around an axis and height along it in units of the object
+    Cylindrical(Axis3),
+    // Planar along each axis blended by the normal raised to the sharpness
+    Triplanar(f64)
+}
+
+// Gives a texture its coordinates from the position of the hit on the object rather than the primitive's own u and v.
+// Positions are taken before Translated, RotateY and Animated move the object so the texture moves with it
+pub struct ProjectedTexture<'tex> {
+    texture : &'tex dyn Texture,
+    mapping : Mapping,
+    centre : Point3
+}
+
+impl<'tex> ProjectedTexture<'tex> {
+    pub fn planar(texture : &'tex dyn Texture, axis : Axis3) -> ProjectedTexture<'tex> {
+        ProjectedTexture::new(texture, Mapping::Planar(axis))
+    }
+
+    pub fn spherical(texture : &'tex dyn Texture, axis : Axis3) -> ProjectedTexture<'tex> {
+        ProjectedTexture::new(texture, Mapping::Spherical(axis))
+    }
+
+    pub fn cylindrical(texture : &'tex dyn Texture, axis : Axis3) -> ProjectedTexture<'tex> {
+        ProjectedTexture::new(texture, Mapping::Cylindrical(axis))
+    }
+
+    // Higher sharpness narrows the seams where the planes blend
+    pub fn triplanar(texture : &'tex dyn Texture, sharpness : f64) -> ProjectedTexture<'tex> {
+        ProjectedTexture::new(texture, Mapping::Triplanar(sharpness.max(1.0)))
+    }
+
+    fn new(texture : &'tex dyn Texture, mapping : Mapping) -> ProjectedTexture<'tex> {
+        ProjectedTexture {
+            texture,
+            mapping,
+            centre : Point3::zero()
+        }
+    }
+
+    // Object space point the projection is made about
+    pub fn set_centre(&mut self, centre : &Point3) {
+        self.centre = *centre;
+    }
+
+    // Looks up the coordinates each projection gives through look, blending the planes for triplanar
+    fn project<T, F>(&self, object_point : &Point3, object_normal : &Vec3, differentials : Option<(Vec3, Vec3)>, look : F) -> T
+    where T : ops::Add<Output = T> + ops::Mul<f64, Output = T>, F : Fn(f64, f64, Option<&Footprint>) -> T {
+        let local = object_point - self.centre;
+        let look_through = |map : &dyn Fn(&Vec3) -> (f64, f64), wraps : bool| {
+            let (u, v, footprint) = coordinates(map, wraps, &local, differentials);
+            look(u, v, footprint.as_ref())
+        };
+        match self.mapping {
+            Mapping::Planar(axis) => look_through(&|local| planar(axis, local), false),
+            Mapping::Spherical(axis) => look_through(&|local| spherical(axis, local), true),
+            Mapping::Cylindrical(axis) => look_through(&|local| cylindrical(axis, local), true),
+            Mapping::Triplanar(sharpness) => {
+                let weights = axes3().map(|axis| object_normal.coord(axis).abs().powf(sharpness));
+                let total : f64 = weights.iter().sum();
+                let mut blend : Option<T> = None;
+                for (axis, weight) in axes3().iter().zip(weights.iter()) {
+                    // Planes barely facing the normal add nothing so skip the lookup
+                    if *weight > 1e-4 * total {
+                        let value = look_through(&|local| planar(*axis, local), false) * (weight / total);
+                        blend = Some(match blend {
+                            Some(sum) => sum + value,
+                            None => value
+                        });
+                    }
+                }
+                // No plane is left only without a normal
+                blend.unwrap_or_else(|| look_through(&|local| planar(Axis3::Y, local), false))
+            }
+        }
+    }
+}
+
+impl<'tex> Texture for ProjectedTexture<'tex> {
+    // Without a hit the point is taken as the object point and as the normal from the centre
+    fn value(&self, u : f64, v : f64, point : &Point3) -> Colour {
+        self.value_at(u, v, point, 0.0)
+    }
+
+    fn value_at(&self, _u : f64, _v : f64, point : &Point3, time : f64) -> Colour {
+        self.project(point, &(point - self.centre), None, |u, v, _| self.texture.value_at(u, v, point, time))
+    }
+
+    // A footprint over the primitive's coordinates says nothing about the projected ones without the
+    // tangents from a hit, so this is unfiltered
+    fn filtered(&self, u : f64, v : f64, point : &Point3, time : f64, _footprint : &Footprint) -> Colour {
+        self.value_at(u, v, point, time)
+    }
+
+    fn value_at_hit(&self, hit_record : &HitRecord) -> Colour {
+        // Steps across the pixel in object space from the footprint along the object's tangents
+        let differentials = hit_record.footprint.map(|footprint| (
+            footprint.dudx * hit_record.object_dpdu + footprint.dvdx * hit_record.object_dpdv,
+            footprint.dudy * hit_record.object_dpdu + footprint.dvdy * hit_record.object_dpdv
+        ));
+        let (point, time) = (&hit_record.point, hit_record.time);
+        self.project(&hit_record.object_point, &hit_record.object_normal, differentials, |u, v, footprint| match footprint {
+            Some(footprint) => self.texture.filtered(u, v, point, time, footprint),
+            None => self.texture.value_at(u, v, point, time)
+        })
+    }
+
+    fn alpha(&self, _u : f64, _v : f64, point : &Point3) -> f64 {
+        self.project(point, &(point - self.centre), None, |u, v, _| self.texture.alpha(u, v, point))
+    }
+
+    fn alpha_at_hit(&self, hit_record : &HitRecord) -> f64 {
+        let point = &hit_record.point;
+        self.project(&hit_record.object_point, &hit_record.object_normal, None, |u, v, _| self.texture.alpha(u, v, point))
+    }
+}
@@ -0,0 +1,104 @@
+use std::f64::consts::PI;
+use std::ops;
+use crate::vec3::{cross, dot, Vec3};
+
+#[derive(Copy, Clone)]
+pub struct Quaternion {
+    w : f64,
+    v : Vec3
+}
+
+impl Quaternion {
+    pub fn identity() -> Quaternion {
+        Quaternion { w : 1.0, v : Vec3::zero() }
+    }
+
+    // Angle in degrees to match RotateY, a zero axis gives no rotation
+    pub fn from_axis_angle(axis : &Vec3, angle : f64) -> Quaternion {
+        if axis.length_squared() == 0.0 {
+            return Quaternion::identity();
+        }
+        let half_angle = angle * PI / 360.0;
+        Quaternion {
+            w : half_angle.cos(),
+            v : half_angle.sin() * axis.unit()
+        }
+    }
+
+    pub fn norm(&self) -> f64 {
+        (self.w * self.w + self.v.length_squared()).sqrt()
+    }
+
+    pub fn unit(&self) -> Quaternion {
+        let n = self.norm();
+        if n == 0.0 {
+            return Quaternion::identity();
+        }
+        Quaternion { w : self.w / n, v : self.v / n }
+    }
+
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion { w : self.w, v : -self.v }
+    }
+
+    pub fn rotate(&self, vec : &Vec3) -> Vec3 {
+        // v' = v + 2w(q x v) + 2 q x (q x v)
+        let t = 2.0 * cross(&self.v, vec);
+        vec + self.w * t + cross(&self.v, &t)
+    }
+
+    pub fn slerp(&self, other : &Quaternion, s : f64) -> Quaternion {
+        let mut cos_th = quaternion_dot(self, other);
+        // Take the short way round
+        let end = if cos_th < 0.0 {
+            cos_th = -cos_th;
+            -*other
+        } else {
+            *other
+        };
+        if cos_th > 0.9995 {
+            // Nearly parallel so lerp is accurate and stable
+            return (*self * (1.0 - s) + end * s).unit();
+        }
+        let th = cos_th.acos();
+        let sin_th = th.sin();
+        let a = ((1.0 - s) * th).sin() / sin_th;
+        let b = (s * th).sin() / sin_th;
+        (*self * a + end * b).unit()
+    }
+}
+
+fn quaternion_dot(a : &Quaternion, b : &Quaternion) -> f64 {
+    a.w * b.w + dot(&a.v, &b.v)
+}
+
+impl ops::Mul<Quaternion> for Quaternion {
+    type Output = Quaternion;
+    fn mul(self, rhs: Quaternion) -> Self::Output {
+        Quaternion {
+            w : self.w * rhs.w - dot(&self.v, &rhs.v),
+            v : self.w * rhs.v + rhs.w * self.v + cross(&self.v, &rhs.v)
+        }
+    }
+}
+
+impl ops::Mul<f64> for Quaternion {
+    type Output = Quaternion;
+    fn mul(self, rhs: f64) -> Self::Output {
+        Quaternion { w : self.w * rhs, v : self.v * rhs }
+    }
+}
+
+impl ops::Add<Quaternion> for Quaternion {
+    type Output = Quaternion;
+    fn add(self, rhs: Quaternion) -> Self::Output {
+        Quaternion { w : self.w + rhs.w, v : self.v + rhs.v }
+    }
+}
+
+impl ops::Neg for Quaternion {
+    type Output = Quaternion;
+    fn neg(self) -> Self::Output {
+        Quaternion { w : -self.w, v : -self.v }
+    }
+}
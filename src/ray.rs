@@ -3,16 +3,28 @@ use crate::vec3::{Vec3, Point3};
 pub struct Ray {
     pub origin : Point3,
     pub direction : Vec3,
-    pub time : f64
+    pub time : f64,
+    // Hero wavelength in nanometres when rendering spectrally
+    pub wavelength : Option<f64>,
+    pub differentials : Option<RayDifferentials>
+}
+
+// Rays offset by a pixel across and down the image, used to find how much of a texture a pixel covers
+#[derive(Copy, Clone)]
+pub struct RayDifferentials {
+    pub x_origin : Point3,
+    pub x_direction : Vec3,
+    pub y_origin : Point3,
+    pub y_direction : Vec3
 }
 
 impl Ray {
     pub fn new(origin : &Point3, direction : &Vec3, time : f64) -> Ray {
-        Ray {origin : *origin, direction : *direction, time}
+        Ray {origin : *origin, direction : *direction, time, wavelength : None, differentials : None}
     }
 
     pub fn between(origin: &Point3, point_on : &Point3, time : f64) -> Ray {
-        Ray {origin : *origin, direction : point_on - origin, time}
+        Ray {origin : *origin, direction : point_on - origin, time, wavelength : None, differentials : None}
     }
 
     pub fn at(&self, t : f64) -> Point3 {
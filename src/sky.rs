@@ -0,0 +1,189 @@
+use std::f64::consts::PI;
+use rand::Rng;
+use rand::rngs::ThreadRng;
+use crate::colour::{xyz_to_rgb, Colour};
+use crate::environment::Environment;
+use crate::lights::LightSample;
+use crate::vec3::{dot, random_unit, OrthonormalBasis, UnitVec3, Vec3};
+
+// Angular radius of the sun in radians
+const SUN_RADIUS : f64 = 0.00465;
+// Sun luminance above the atmosphere in the sky units of kcd/m^2
+const SUN_LUMINANCE : f64 = 2.0e6;
+// Intensity that brings the sky units of kcd/m^2 down to about the brightness of the scenes lit by lamps,
+// a clear zenith of a few kcd/m^2 renders at around a tenth
+pub const DAYLIGHT_INTENSITY : f64 = 0.025;
+// Chance of sampling towards the sun rather than the whole sky
+const SUN_SAMPLE_PROBABILITY : f64 = 0.5;
+
+// Perez distribution coefficients A to E for luminance and chromaticity, linear in turbidity
+const PEREZ_Y : [[f64; 2]; 5] = [[0.1787, -1.4630], [-0.3554, 0.4275], [-0.0227, 5.3251], [0.1206, -2.5771], [-0.0670, 0.3703]];
+const PEREZ_X : [[f64; 2]; 5] = [[-0.0193, -0.2592], [-0.0665, 0.0008], [-0.0004, 0.2125], [-0.0641, -0.8989], [-0.0033, 0.0452]];
+const PEREZ_LITTLE_Y : [[f64; 2]; 5] = [[-0.0167, -0.2608], [-0.0950, 0.0092], [-0.0079, 0.2102], [-0.0441, -1.6537], [-0.0109, 0.0529]];
+
+// Zenith chromaticity polynomials in turbidity and sun angle
+const ZENITH_X : [[f64; 4]; 3] = [
+    [0.00166, -0.00375, 0.00209, 0.0],
+    [-0.02903, 0.06377, -0.03202, 0.00394],
+    [0.11693, -0.21196, 0.06052, 0.25886]
+];
+const ZENITH_LITTLE_Y : [[f64; 4]; 3] = [
+    [0.00275, -0.00610, 0.00317, 0.0],
+    [-0.04214, 0.08970, -0.04153, 0.00516],
+    [0.15346, -0.26756, 0.06670, 0.26688]
+];
+
+fn perez_coefficients(table : &[[f64; 2]; 5], turbidity : f64) -> [f64; 5] {
+    table.map(|[a, b]| a * turbidity + b)
+}
+
+fn perez(coeffs : &[f64; 5], cos_th : f64, gamma : f64) -> f64 {
+    let [a, b, c, d, e] = *coeffs;
+    (1.0 + a * (b / cos_th).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+fn zenith_chromaticity(table : &[[f64; 4]; 3], turbidity : f64, sun_theta : f64) -> f64 {
+    let t = [turbidity * turbidity, turbidity, 1.0];
+    let th = [sun_theta.powi(3), sun_theta.powi(2), sun_theta, 1.0];
+    let mut total = 0.0;
+    for (row, t_val) in table.iter().zip(t.iter()) {
+        for (val, th_val) in row.iter().zip(th.iter()) {
+            total += t_val * val * th_val;
+        }
+    }
+    total
+}
+
+// Rayleigh and aerosol transmittance of sunlight through the atmosphere at red, green and blue
+fn sun_transmittance(turbidity : f64, sun_theta : f64) -> Colour {
+    let theta_deg = sun_theta * 180.0 / PI;
+    let mass = 1.0 / (sun_theta.cos() + 0.15 * (93.885 - theta_deg).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let channel = |lambda : f64| {
+        let rayleigh = (-0.008735 * lambda.powf(-4.08) * mass).exp();
+        let aerosol = (-beta * lambda.powf(-1.3) * mass).exp();
+        rayleigh * aerosol
+    };
+    Colour::new(channel(0.68), channel(0.55), channel(0.44))
+}
+
+// Preetham et al. analytic daylight model
+pub struct PreethamSky {
+    sun_direction : UnitVec3,
+    sun_basis : OrthonormalBasis,
+    sun_theta : f64,
+    zenith : [f64; 3],
+    coeffs : [[f64; 5]; 3],
+    sun_radiance : Colour,
+    cos_sun_radius : f64,
+    intensity : f64
+}
+
+impl PreethamSky {
+    // Angles in degrees, azimuth turns from +x towards -z, turbidity from 2 (clear) to 10 (hazy)
+    pub fn new(elevation : f64, azimuth : f64, turbidity : f64, intensity : f64) -> PreethamSky {
+        let elevation_rad = elevation * PI / 180.0;
+        let azimuth_rad = azimuth * PI / 180.0;
+        let sun_direction = Vec3::new(
+            elevation_rad.cos() * azimuth_rad.cos(),
+            elevation_rad.sin(),
+            -elevation_rad.cos() * azimuth_rad.sin()
+        );
+        // Model is only fitted with the sun above the horizon
+        let sun_theta = (PI / 2.0 - elevation_rad).clamp(0.0, PI / 2.0);
+        let chi = (4.0 / 9.0 - turbidity / 120.0) * (PI - 2.0 * sun_theta);
+        let zenith_luminance = (4.0453 * turbidity - 4.9710) * chi.tan() - 0.2155 * turbidity + 2.4192;
+        let zenith = [
+            zenith_luminance.max(0.0),
+            zenith_chromaticity(&ZENITH_X, turbidity, sun_theta),
+            zenith_chromaticity(&ZENITH_LITTLE_Y, turbidity, sun_theta)
+        ];
+        let coeffs = [
+            perez_coefficients(&PEREZ_Y, turbidity),
+            perez_coefficients(&PEREZ_X, turbidity),
+            perez_coefficients(&PEREZ_LITTLE_Y, turbidity)
+        ];
+        let sun_radiance = if elevation > 0.0 {
+            SUN_LUMINANCE * sun_transmittance(turbidity, sun_theta)
+        } else {
+            Colour::zero()
+        };
+        PreethamSky {
+            sun_direction,
+            sun_basis : OrthonormalBasis::new(&sun_direction),
+            sun_theta,
+            zenith,
+            coeffs,
+            sun_radiance,
+            cos_sun_radius : SUN_RADIUS.cos(),
+            intensity
+        }
+    }
+
+    fn sky(&self, direction : &UnitVec3) -> Colour {
+        // Below the horizon repeat the horizon
+        let cos_th = direction.y().max(0.01);
+        let cos_gamma = dot(direction, &self.sun_direction).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+        let mut yxy = [0.0; 3];
+        for (k, val) in yxy.iter_mut().enumerate() {
+            let ratio = perez(&self.coeffs[k], cos_th, gamma) / perez(&self.coeffs[k], 1.0, self.sun_theta);
+            *val = self.zenith[k] * ratio;
+        }
+        let [luminance, x, y] = yxy;
+        if y <= 0.0 {
+            return Colour::zero();
+        }
+        xyz_to_rgb(x / y * luminance, luminance, (1.0 - x - y) / y * luminance)
+    }
+
+    fn sun_visible(&self) -> bool {
+        self.sun_radiance.length_squared() > 0.0
+    }
+
+    fn in_sun(&self, direction : &UnitVec3) -> bool {
+        dot(direction, &self.sun_direction) >= self.cos_sun_radius
+    }
+
+    fn sun_pdf(&self) -> f64 {
+        1.0 / (2.0 * PI * (1.0 - self.cos_sun_radius))
+    }
+}
+
+impl Environment for PreethamSky {
+    fn value(&self, direction : &Vec3) -> Colour {
+        let unit = direction.unit();
+        let mut colour = self.sky(&unit);
+        if self.sun_visible() && self.in_sun(&unit) {
+            colour += self.sun_radiance;
+        }
+        self.intensity * colour
+    }
+
+    fn sample(&self, rng : &mut ThreadRng) -> Option<LightSample> {
+        let direction = if self.sun_visible() && rng.gen::<f64>() < SUN_SAMPLE_PROBABILITY {
+            let cos_th = 1.0 - rng.gen::<f64>() * (1.0 - self.cos_sun_radius);
+            let sin_th = (1.0 - cos_th * cos_th).max(0.0).sqrt();
+            let phi = 2.0 * PI * rng.gen::<f64>();
+            self.sun_basis.to_world(&Vec3::new(sin_th * phi.cos(), sin_th * phi.sin(), cos_th))
+        } else {
+            random_unit(rng)
+        };
+        Some(LightSample {
+            direction,
+            distance : f64::MAX,
+            radiance : self.value(&direction),
+            pdf : self.pdf(&direction)
+        })
+    }
+
+    fn pdf(&self, direction : &Vec3) -> f64 {
+        let uniform = 1.0 / (4.0 * PI);
+        if !self.sun_visible() {
+            return uniform;
+        }
+        let unit = direction.unit();
+        let sun = if self.in_sun(&unit) { self.sun_pdf() } else { 0.0 };
+        SUN_SAMPLE_PROBABILITY * sun + (1.0 - SUN_SAMPLE_PROBABILITY) * uniform
+    }
+}
@@ -0,0 +1,190 @@
+use std::sync::OnceLock;
+use rand::Rng;
+use crate::colour::{xyz_to_rgb, Colour};
+
+// Visible range in nanometres
+pub const LAMBDA_MIN : f64 = 380.0;
+pub const LAMBDA_MAX : f64 = 720.0;
+// Wavelength used for dispersive materials when not rendering spectrally (sodium d-line)
+pub const LAMBDA_REFERENCE : f64 = 587.6;
+// Representative wavelengths of the red, green and blue channels for effects computed per channel
+pub const RGB_WAVELENGTHS : [f64; 3] = [630.0, 532.0, 465.0];
+// Wavelengths carried by each path, one per channel of a Colour
+const SAMPLE_COUNT : usize = 3;
+
+// Piecewise gaussian with different widths either side of the mean
+fn lobe(lambda : f64, mean : f64, sigma_low : f64, sigma_high : f64) -> f64 {
+    let sigma = if lambda < mean { sigma_low } else { sigma_high };
+    let t = (lambda - mean) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+// Multi-lobe fit to the CIE 1931 observer (Wyman, Sloan and Shirley 2013)
+pub fn cie_xyz(lambda : f64) -> Colour {
+    let x = 1.056 * lobe(lambda, 599.8, 37.9, 31.0)
+        + 0.362 * lobe(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(lambda, 568.8, 46.9, 40.5)
+        + 0.286 * lobe(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(lambda, 437.0, 11.8, 36.0)
+        + 0.681 * lobe(lambda, 459.0, 26.0, 13.8);
+    Colour::new(x, y, z)
+}
+
+fn smoothstep(edge0 : f64, edge1 : f64, x : f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Smooth bands splitting the visible range into blue, green and red
+fn blue_band(lambda : f64) -> f64 {
+    1.0 - smoothstep(480.0, 500.0, lambda)
+}
+
+fn red_band(lambda : f64) -> f64 {
+    smoothstep(575.0, 595.0, lambda)
+}
+
+fn green_band(lambda : f64) -> f64 {
+    1.0 - blue_band(lambda) - red_band(lambda)
+}
+
+// Smits style upsampling, white plus the secondary and primary bands that make up the rest
+pub fn rgb_to_spectrum(colour : &Colour, lambda : f64) -> f64 {
+    let (r, g, b) = (colour.x(), colour.y(), colour.z());
+    let blue = blue_band(lambda);
+    let green = green_band(lambda);
+    let red = red_band(lambda);
+    if r <= g && r <= b {
+        let cyan = blue + green;
+        r + if g <= b {
+            (g - r) * cyan + (b - g) * blue
+        } else {
+            (b - r) * cyan + (g - b) * green
+        }
+    } else if g <= r && g <= b {
+        let magenta = blue + red;
+        g + if r <= b {
+            (r - g) * magenta + (b - r) * blue
+        } else {
+            (b - g) * magenta + (r - b) * red
+        }
+    } else {
+        let yellow = green + red;
+        b + if r <= g {
+            (r - b) * yellow + (g - r) * green
+        } else {
+            (g - b) * yellow + (r - g) * red
+        }
+    }
+}
+
+// Integral of the y matching function and the rgb of a flat spectrum used to white balance
+struct Normalisation {
+    y_integral : f64,
+    white : Colour
+}
+
+fn normalisation() -> &'static Normalisation {
+    static NORMALISATION : OnceLock<Normalisation> = OnceLock::new();
+    NORMALISATION.get_or_init(|| {
+        let steps = 1000;
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+        let mut xyz = Colour::zero();
+        for i in 0..steps {
+            xyz += cie_xyz(LAMBDA_MIN + (i as f64 + 0.5) * step) * step;
+        }
+        let y_integral = xyz.y();
+        xyz /= y_integral;
+        Normalisation {
+            y_integral,
+            white : xyz_to_rgb(xyz.x(), xyz.y(), xyz.z())
+        }
+    })
+}
+
+// Planck's law for the spectral radiance of a black body, wavelength in nanometres
+fn planck(lambda : f64, kelvin : f64) -> f64 {
+    const PLANCK : f64 = 6.62607015e-34;
+    const LIGHT_SPEED : f64 = 2.99792458e8;
+    const BOLTZMANN : f64 = 1.380649e-23;
+    let l = lambda * 1e-9;
+    2.0 * PLANCK * LIGHT_SPEED * LIGHT_SPEED / (l.powi(5) * ((PLANCK * LIGHT_SPEED / (l * BOLTZMANN * kelvin)).exp() - 1.0))
+}
+
+// Colour of a black body at a temperature in kelvin, unit brightness and white balanced like a render
+pub fn blackbody(kelvin : f64) -> Colour {
+    let norm = normalisation();
+    let steps = 340;
+    let step = (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+    let mut xyz = Colour::zero();
+    for i in 0..steps {
+        let lambda = LAMBDA_MIN + (i as f64 + 0.5) * step;
+        xyz += cie_xyz(lambda) * planck(lambda, kelvin);
+    }
+    xyz /= xyz.y();
+    let rgb = xyz_to_rgb(xyz.x(), xyz.y(), xyz.z());
+    Colour::new(
+        (rgb.x() / norm.white.x()).max(0.0),
+        (rgb.y() / norm.white.y()).max(0.0),
+        (rgb.z() / norm.white.z()).max(0.0)
+    )
+}
+
+// Hero wavelength sampling, the rest are evenly spaced after the hero
+#[derive(Copy, Clone)]
+pub struct SampledWavelengths {
+    lambdas : [f64; SAMPLE_COUNT],
+    terminated : bool
+}
+
+impl SampledWavelengths {
+    pub fn sample<R>(rng : &mut R) -> SampledWavelengths
+    where R : Rng
+    {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let hero = rng.gen::<f64>() * range;
+        let mut lambdas = [0.0; SAMPLE_COUNT];
+        for (i, lambda) in lambdas.iter_mut().enumerate() {
+            let offset = (hero + i as f64 * range / SAMPLE_COUNT as f64) % range;
+            *lambda = LAMBDA_MIN + offset;
+        }
+        SampledWavelengths { lambdas, terminated : false }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.lambdas[0]
+    }
+
+    // Spectral value of an rgb colour at each wavelength
+    pub fn upsample(&self, colour : &Colour) -> Colour {
+        Colour::new(
+            rgb_to_spectrum(colour, self.lambdas[0]),
+            rgb_to_spectrum(colour, self.lambdas[1]),
+            rgb_to_spectrum(colour, self.lambdas[2])
+        )
+    }
+
+    // After dispersion only the hero wavelength is still valid, weight to keep the estimate unbiased
+    pub fn terminate_secondary(&self) -> (SampledWavelengths, Colour) {
+        if self.terminated {
+            return (*self, Colour::new(1.0, 1.0, 1.0));
+        }
+        let terminated = SampledWavelengths { lambdas : self.lambdas, terminated : true };
+        (terminated, Colour::new(SAMPLE_COUNT as f64, 0.0, 0.0))
+    }
+
+    // Estimate of the rgb colour from the radiance carried at each wavelength
+    pub fn resolve(&self, values : &Colour) -> Colour {
+        let norm = normalisation();
+        let pdf = 1.0 / (LAMBDA_MAX - LAMBDA_MIN);
+        let samples = [values.x(), values.y(), values.z()];
+        let mut xyz = Colour::zero();
+        for (lambda, value) in self.lambdas.iter().zip(samples.iter()) {
+            xyz += cie_xyz(*lambda) * (value / pdf);
+        }
+        xyz /= SAMPLE_COUNT as f64 * norm.y_integral;
+        let rgb = xyz_to_rgb(xyz.x(), xyz.y(), xyz.z());
+        Colour::new(rgb.x() / norm.white.x(), rgb.y() / norm.white.y(), rgb.z() / norm.white.z())
+    }
+}
@@ -1,14 +1,100 @@
 use std::collections::HashMap;
-use image::RgbImage;
+use image::DynamicImage;
 use rand::Rng;
 use rand::seq::SliceRandom;
-use crate::colour::{read_colour, Colour};
-use crate::interval::Interval;
+use crate::assets::{AssetError, AssetResolver};
+use crate::colour::{Colour, ColourSpace};
+use crate::hittables::HitRecord;
+use crate::mipmap::{Filter, MipFilter, MipMap, Texel, Wrap};
 use crate::perlin::Perlin;
-use crate::vec3::Point3;
+use crate::worley::{CellFeature, Worley};
+use crate::vec3::{Point3, Vec3};
 
+// Textures built from other textures look their inputs up the same way they were looked up, so
+// filtering, projections and time carry on through to the inputs
 pub trait Texture : Sync {
     fn value(&self, u : f64, v : f64, point : &Point3) -> Colour;
+
+    // Coverage of the surface, textures without an alpha channel are opaque
+    fn alpha(&self, _u : f64, _v : f64, _point : &Point3) -> f64 {
+        1.0
+    }
+
+    // Coverage at a hit, textures that move the coordinates pass the moved hit on
+    fn alpha_at_hit(&self, hit_record : &HitRecord) -> f64 {
+        self.alpha(hit_record.u, hit_record.v, &hit_record.point)
+    }
+
+    // Value at a time during the shutter, textures that do not change ignore it
+    fn value_at(&self, u : f64, v : f64, point : &Point3, _time : f64) -> Colour {
+        self.value(u, v, point)
+    }
+
+    // Value averaged over the footprint of a pixel, textures that cannot alias ignore it
+    fn filtered(&self, u : f64, v : f64, point : &Point3, time : f64, _footprint : &Footprint) -> Colour {
+        self.value_at(u, v, point, time)
+    }
+
+    // Value at a hit and its time, filtered over the footprint when there is one.
+    // Projections use the rest of the hit to make their own coordinates
+    fn value_at_hit(&self, hit_record : &HitRecord) -> Colour {
+        match &hit_record.footprint {
+            Some(footprint) => self.filtered(hit_record.u, hit_record.v, &hit_record.point, hit_record.time, footprint),
+            None => self.value_at(hit_record.u, hit_record.v, &hit_record.point, hit_record.time)
+        }
+    }
+}
+
+// Textures that only combine their inputs at a point, every kind of lookup is passed on to the inputs
+pub trait Node : Sync {
+    fn combine(&self, u : f64, v : f64, point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour;
+}
+
+impl<T : Node> Texture for T {
+    fn value(&self, u : f64, v : f64, point : &Point3) -> Colour {
+        self.value_at(u, v, point, 0.0)
+    }
+
+    fn value_at(&self, u : f64, v : f64, point : &Point3, time : f64) -> Colour {
+        self.combine(u, v, point, &|texture| texture.value_at(u, v, point, time))
+    }
+
+    fn filtered(&self, u : f64, v : f64, point : &Point3, time : f64, footprint : &Footprint) -> Colour {
+        self.combine(u, v, point, &|texture| texture.filtered(u, v, point, time, footprint))
+    }
+
+    fn value_at_hit(&self, hit_record : &HitRecord) -> Colour {
+        self.combine(hit_record.u, hit_record.v, &hit_record.point, &|texture| texture.value_at_hit(hit_record))
+    }
+}
+
+// How far the texture coordinates move across a pixel in x and y
+#[derive(Copy, Clone)]
+pub struct Footprint {
+    pub dudx : f64,
+    pub dvdx : f64,
+    pub dudy : f64,
+    pub dvdy : f64
+}
+
+// Single number driving a material, either fixed or the average of a texture's channels
+#[derive(Copy, Clone)]
+pub enum Scalar<'tex> {
+    Value(f64),
+    Texture(&'tex dyn Texture)
+}
+
+impl<'tex> Scalar<'tex> {
+    // Looked up through the hit so filtering, projections and time reach the texture
+    pub fn value_at_hit(&self, hit_record : &HitRecord) -> f64 {
+        match self {
+            Scalar::Value(value) => *value,
+            Scalar::Texture(texture) => {
+                let colour = texture.value_at_hit(hit_record);
+                (colour.x() + colour.y() + colour.z()) / 3.0
+            }
+        }
+    }
 }
 
 pub struct SolidColour {
@@ -42,43 +128,99 @@ impl<'tex> Checker<'tex> {
             odd
         }
     }
-}
 
-impl<'tex> Texture for Checker<'tex> {
-    fn value(&self, u: f64, v: f64, point: &Point3) -> Colour {
+    fn choose(&self, point : &Point3) -> &'tex dyn Texture {
         let x_int = (self.inv_scale * point.x()).floor() as i64;
         let y_int = (self.inv_scale * point.y()).floor() as i64;
         let z_int = (self.inv_scale * point.z()).floor() as i64;
-        if (x_int + y_int + z_int) % 2 == 0 {
-            self.even.value(u, v, point)
-        } else {
-            self.odd.value(u, v, point)
-        }
+        if (x_int + y_int + z_int) % 2 == 0 { self.even } else { self.odd }
+    }
+}
+
+impl<'tex> Node for Checker<'tex> {
+    fn combine(&self, _u : f64, _v : f64, point : &Point3, lookup : &dyn Fn(&dyn Texture) -> Colour) -> Colour {
+        lookup(self.choose(point))
     }
 }
 
+// Nearest texel and clamped by default, filtering and wrapping can be set
 pub struct ImageTexture {
-    data : RgbImage
+    mipmap : MipMap,
+    filter : Filter,
+    wrap : Wrap,
+    mip_filter : MipFilter,
+    scale : (f64, f64),
+    offset : (f64, f64)
 }
 
 impl ImageTexture {
-    pub fn load(name : &str) -> Option<ImageTexture> {
-        let path = "./data/".to_string() + name;
-        let image = image::open(path).ok()?;
-        Some(ImageTexture {
-            data: image.into_rgb8()
+    // Colours are decoded from the space to linear light before filtering, alpha is always linear.
+    // Texels are floats so 16-bit images keep their precision and HDR values above one are kept
+    pub fn load(assets : &AssetResolver, name : &str, space : ColourSpace) -> Result<ImageTexture, AssetError> {
+        let image = assets.load_image(name)?;
+        // Floating point formats such as .hdr and .exr already hold linear light
+        let space = match image {
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => ColourSpace::Linear,
+            _ => space
+        };
+        let mut image = image.into_rgba32f();
+        for pixel in image.pixels_mut() {
+            for channel in pixel.0.iter_mut().take(3) {
+                *channel = space.decode(*channel as f64) as f32;
+            }
+        }
+        Ok(ImageTexture {
+            mipmap : MipMap::new(&image),
+            filter : Filter::Nearest,
+            wrap : Wrap::Clamp,
+            mip_filter : MipFilter::Off,
+            scale : (1.0, 1.0),
+            offset : (0.0, 0.0)
         })
     }
+
+    pub fn set_filter(&mut self, filter : Filter, mip_filter : MipFilter) {
+        self.filter = filter;
+        self.mip_filter = mip_filter;
+    }
+
+    pub fn set_wrap(&mut self, wrap : Wrap) {
+        self.wrap = wrap;
+    }
+
+    // Texture coordinates are scaled then offset before looking up the image
+    pub fn set_uv_transform(&mut self, scale_u : f64, scale_v : f64, offset_u : f64, offset_v : f64) {
+        self.scale = (scale_u, scale_v);
+        self.offset = (offset_u, offset_v);
+    }
+
+    fn sample(&self, u : f64, v : f64) -> Texel {
+        let (u, v) = (u * self.scale.0 + self.offset.0, v * self.scale.1 + self.offset.1);
+        self.mipmap.sample(u, v, &self.filter, &self.wrap)
+    }
 }
 
 impl Texture for ImageTexture {
     fn value(&self, u: f64, v: f64, _point: &Point3) -> Colour {
-        let unit = Interval::new(0.0, 1.0);
-        let local_u = unit.clamp(u);
-        let local_v = 1.0 - unit.clamp(v);
-        let i = (local_u * (self.data.width() as f64)) as u32;
-        let j = (local_v * (self.data.height() as f64)) as u32;
-        read_colour(&self.data, i, j)
+        let texel = self.sample(u, v);
+        Colour::new(texel[0], texel[1], texel[2])
+    }
+
+    fn alpha(&self, u : f64, v : f64, _point : &Point3) -> f64 {
+        self.sample(u, v)[3]
+    }
+
+    fn filtered(&self, u : f64, v : f64, _point : &Point3, _time : f64, footprint : &Footprint) -> Colour {
+        let (scale_u, scale_v) = self.scale;
+        let scaled = Footprint {
+            dudx : footprint.dudx * scale_u,
+            dvdx : footprint.dvdx * scale_v,
+            dudy : footprint.dudy * scale_u,
+            dvdy : footprint.dvdy * scale_v
+        };
+        let (u, v) = (u * scale_u + self.offset.0, v * scale_v + self.offset.1);
+        let texel = self.mipmap.filtered(u, v, &scaled, &self.filter, &self.wrap, &self.mip_filter);
+        Colour::new(texel[0], texel[1], texel[2])
     }
 }
 
@@ -107,6 +249,156 @@ impl Texture for MarbleTexture {
     }
 }
 
+// How octaves of Perlin noise are summed
+#[derive(Copy, Clone)]
+pub enum Fractal {
+    // Soft billows for clouds
+    Fbm,
+    // Sharp crests for mountain ranges
+    Ridged
+}
+
+// Fractal noise in [0, 1], six octaves doubling in frequency and halving in amplitude by default.
+// Still unless given a speed
+pub struct FractalTexture {
+    fractal : Fractal,
+    scale : f64,
+    octaves : usize,
+    lacunarity : f64,
+    gain : f64,
+    speed : f64,
+    noise : Perlin
+}
+
+impl FractalTexture {
+    pub fn new<R>(rng : &mut R, fractal : Fractal, scale : f64) -> FractalTexture
+    where R : Rng
+    {
+        FractalTexture::with_noise(Perlin::new(rng), fractal, scale)
+    }
+
+    // Built on given noise, such as seeded or tiling noise
+    pub fn with_noise(noise : Perlin, fractal : Fractal, scale : f64) -> FractalTexture {
+        FractalTexture {
+            fractal,
+            scale,
+            octaves : 6,
+            lacunarity : 2.0,
+            gain : 0.5,
+            speed : 0.0,
+            noise
+        }
+    }
+
+    // Lattice cells of noise the pattern moves through each unit of time
+    pub fn set_speed(&mut self, speed : f64) {
+        self.speed = speed;
+    }
+
+    pub fn set_octaves(&mut self, octaves : usize, lacunarity : f64, gain : f64) {
+        self.octaves = octaves;
+        self.lacunarity = lacunarity;
+        self.gain = gain;
+    }
+}
+
+impl Texture for FractalTexture {
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Colour {
+        self.value_at(u, v, point, 0.0)
+    }
+
+    fn value_at(&self, _u : f64, _v : f64, point : &Point3, time : f64) -> Colour {
+        let scaled = self.scale * point;
+        let (octaves, lacunarity, gain) = (self.octaves, self.lacunarity, self.gain);
+        // Still noise skips the time dimension as it is half the cost
+        let val = match (self.fractal, self.speed == 0.0) {
+            (Fractal::Fbm, true) => 0.5 + 0.5 * self.noise.fbm(&scaled, octaves, lacunarity, gain),
+            (Fractal::Fbm, false) => 0.5 + 0.5 * self.noise.fbm_4d(&scaled, self.speed * time, octaves, lacunarity, gain),
+            (Fractal::Ridged, true) => self.noise.ridged(&scaled, octaves, lacunarity, gain),
+            (Fractal::Ridged, false) => self.noise.ridged_4d(&scaled, self.speed * time, octaves, lacunarity, gain)
+        }.clamp(0.0, 1.0);
+        Colour::new(val, val, val)
+    }
+}
+
+// Distance to random points scattered one per cell, for stone, scales and cracked earth
+pub struct CellularTexture {
+    feature : CellFeature,
+    scale : f64,
+    noise : Worley
+}
+
+impl CellularTexture {
+    pub fn new<R>(rng : &mut R, feature : CellFeature, scale : f64) -> CellularTexture
+    where R : Rng
+    {
+        CellularTexture {
+            feature,
+            scale,
+            noise : Worley::new(rng)
+        }
+    }
+}
+
+impl Texture for CellularTexture {
+    fn value(&self, _u: f64, _v: f64, point: &Point3) -> Colour {
+        let val = self.noise.feature(&(self.scale * point), self.feature).clamp(0.0, 1.0);
+        Colour::new(val, val, val)
+    }
+}
+
+// Samples another texture at points pushed around by fBm, which folds and swirls solid textures
+pub struct WarpedTexture<'tex> {
+    texture : &'tex dyn Texture,
+    scale : f64,
+    strength : f64,
+    noise : Perlin
+}
+
+impl<'tex> WarpedTexture<'tex> {
+    pub fn new<R>(rng : &mut R, texture : &'tex dyn Texture, scale : f64, strength : f64) -> WarpedTexture<'tex>
+    where R : Rng
+    {
+        WarpedTexture {
+            texture,
+            scale,
+            strength,
+            noise : Perlin::new(rng)
+        }
+    }
+
+    fn warp(&self, point : &Point3) -> Point3 {
+        let scaled = self.scale * point;
+        // Each component reads the noise far from the others so they are unrelated
+        let offset = Vec3::new(
+            self.noise.fbm(&scaled, 4, 2.0, 0.5),
+            self.noise.fbm(&(scaled + Vec3::new(5.2, 1.3, 2.8)), 4, 2.0, 0.5),
+            self.noise.fbm(&(scaled + Vec3::new(1.7, 9.2, 8.3)), 4, 2.0, 0.5)
+        );
+        point + self.strength * offset
+    }
+}
+
+impl<'tex> Texture for WarpedTexture<'tex> {
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Colour {
+        self.value_at(u, v, point, 0.0)
+    }
+
+    fn value_at(&self, u : f64, v : f64, point : &Point3, time : f64) -> Colour {
+        self.texture.value_at(u, v, &self.warp(point), time)
+    }
+
+    fn filtered(&self, u : f64, v : f64, point : &Point3, time : f64, footprint : &Footprint) -> Colour {
+        self.texture.filtered(u, v, &self.warp(point), time, footprint)
+    }
+
+    // Only the world point is warped, projections below still read the object point
+    fn value_at_hit(&self, hit_record : &HitRecord) -> Colour {
+        let warped = HitRecord { point : self.warp(&hit_record.point), ..*hit_record };
+        self.texture.value_at_hit(&warped)
+    }
+}
+
 pub struct TextureWorld<'tex> {
     textures : HashMap<String, Vec<Box<dyn Texture + 'tex>>>
 }
@@ -119,7 +411,7 @@ impl<'tex> TextureWorld<'tex> {
         }
     }
     pub fn add(&mut self, test_type : &str, texture : Box<dyn Texture + 'tex>) {
-        self.textures.entry(test_type.to_string()).or_insert(Vec::new()).push(texture)
+        self.textures.entry(test_type.to_string()).or_default().push(texture)
     }
 
     pub fn chose<'a, R>(&'a self, test_type : &str, rng : &mut R) -> Option<&'a dyn Texture>
@@ -97,6 +97,47 @@ where R : Rng
     }
 }
 
+// Frame with w along the given direction
+pub struct OrthonormalBasis {
+    u : UnitVec3,
+    v : UnitVec3,
+    w : UnitVec3
+}
+
+impl OrthonormalBasis {
+    pub fn new(direction : &Vec3) -> OrthonormalBasis {
+        let w = direction.unit();
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = cross(&w, &a).unit();
+        let u = cross(&w, &v);
+        OrthonormalBasis { u, v, w }
+    }
+
+    // Basis with u along the tangent projected into the plane, any basis when there is no tangent in the plane
+    pub fn with_tangent(direction : &Vec3, tangent : &Vec3) -> OrthonormalBasis {
+        let w = direction.unit();
+        let u = tangent - dot(tangent, &w) * w;
+        if u.length_squared() <= 1e-12 * tangent.length_squared() {
+            return OrthonormalBasis::new(direction);
+        }
+        let u = u.unit();
+        let v = cross(&w, &u);
+        OrthonormalBasis { u, v, w }
+    }
+
+    pub fn to_world(&self, local : &Vec3) -> Vec3 {
+        local.x() * self.u + local.y() * self.v + local.z() * self.w
+    }
+
+    pub fn to_local(&self, world : &Vec3) -> Vec3 {
+        Vec3::new(dot(world, &self.u), dot(world, &self.v), dot(world, &self.w))
+    }
+}
+
 pub fn reflect(v : &Vec3, normal : &UnitVec3) -> Vec3 {
     v - 2.0 * dot(v, normal) * normal
 }
@@ -135,6 +176,8 @@ impl ops::Add<&Vec3> for &Vec3 {
 
 impl ops::Add<Vec3> for &Vec3 {
     type Output = Vec3;
+    // By value operators forward to the impls on references
+    #[allow(clippy::op_ref)]
     fn add(self, rhs: Vec3) -> Self::Output {
         self + &rhs
     }
@@ -142,6 +185,7 @@ impl ops::Add<Vec3> for &Vec3 {
 
 impl ops::Add<&Vec3> for Vec3 {
     type Output = Vec3;
+    #[allow(clippy::op_ref)]
     fn add(self, rhs: &Vec3) -> Self::Output {
         &self + rhs
     }
@@ -165,6 +209,7 @@ impl ops::Sub<&Vec3> for &Vec3 {
 
 impl ops::Sub<Vec3> for &Vec3 {
     type Output = Vec3;
+    #[allow(clippy::op_ref)]
     fn sub(self, rhs: Vec3) -> Self::Output {
         self - &rhs
     }
@@ -172,6 +217,7 @@ impl ops::Sub<Vec3> for &Vec3 {
 
 impl ops::Sub<&Vec3> for Vec3 {
     type Output = Vec3;
+    #[allow(clippy::op_ref)]
     fn sub(self, rhs: &Vec3) -> Self::Output {
         &self - rhs
     }
@@ -195,6 +241,7 @@ impl ops::Mul<f64> for &Vec3 {
 
 impl ops::Mul<f64> for Vec3 {
     type Output = Vec3;
+    #[allow(clippy::op_ref)]
     fn mul(self, rhs: f64) -> Self::Output {
         &self * rhs
     }
@@ -211,6 +258,7 @@ impl ops::Mul<&Vec3> for f64 {
 
 impl ops::Mul<Vec3> for f64 {
     type Output = Vec3;
+    #[allow(clippy::op_ref)]
     fn mul(self, rhs: Vec3) -> Self::Output {
         self * &rhs
     }
@@ -227,6 +275,7 @@ impl ops::Div<f64> for &Vec3 {
 
 impl ops::Div<f64> for Vec3 {
     type Output = Vec3;
+    #[allow(clippy::op_ref)]
     fn div(self, rhs: f64) -> Self::Output {
         &self / rhs
     }
@@ -0,0 +1,101 @@
+use rand::Rng;
+use crate::perlin::{make_permute, POINT_COUNT};
+use crate::vec3::{Point3, Vec3};
+
+// Which distances to the nearest feature points make up the value
+#[derive(Copy, Clone)]
+pub enum CellFeature {
+    // Nearest point, round cells
+    F1,
+    // Second nearest point, bulging cells
+    F2,
+    // Difference of the two, thin cracks along cell borders
+    F2MinusF1
+}
+
+// Cellular noise with one randomly placed feature point in each unit cell (Worley 1996)
+pub struct Worley {
+    offsets : [Vec3; POINT_COUNT],
+    x_permute : [u16; POINT_COUNT],
+    y_permute : [u16; POINT_COUNT],
+    z_permute : [u16; POINT_COUNT]
+}
+
+// Squared distance from the point to the nearest part of the unit cell at the corner
+fn cell_distance_squared(point : &Point3, corner : &Point3) -> f64 {
+    let gap = |p : f64, low : f64| (low - p).max(p - (low + 1.0)).max(0.0);
+    let (dx, dy, dz) = (gap(point.x(), corner.x()), gap(point.y(), corner.y()), gap(point.z(), corner.z()));
+    dx * dx + dy * dy + dz * dz
+}
+
+impl Worley {
+    pub fn new<R>(rng : &mut R) -> Worley
+    where R : Rng
+    {
+        let mut offsets : [Vec3; POINT_COUNT] = [Vec3::zero(); POINT_COUNT];
+        offsets.iter_mut().for_each(
+            |val| *val = Vec3::new(rng.gen(), rng.gen(), rng.gen())
+        );
+        Worley {
+            offsets,
+            x_permute : make_permute(rng),
+            y_permute : make_permute(rng),
+            z_permute : make_permute(rng)
+        }
+    }
+
+    // Distances to the nearest and second nearest feature points. Cells are searched in shells
+    // around the point's cell until no further cell could hold a point nearer than the second
+    pub fn distances(&self, point : &Point3) -> (f64, f64) {
+        let cell = [point.x().floor() as i64, point.y().floor() as i64, point.z().floor() as i64];
+        let mut nearest = f64::MAX;
+        let mut second = f64::MAX;
+        let mut radius : i64 = 0;
+        loop {
+            for dx in -radius..=radius {
+                for dy in -radius..=radius {
+                    for dz in -radius..=radius {
+                        if dx.abs().max(dy.abs()).max(dz.abs()) != radius {
+                            continue;
+                        }
+                        let corner = Point3::new((cell[0] + dx) as f64, (cell[1] + dy) as f64, (cell[2] + dz) as f64);
+                        if cell_distance_squared(point, &corner) >= second {
+                            continue;
+                        }
+                        let feature = corner + self.offset(&corner);
+                        let distance = (feature - point).length_squared();
+                        if distance < nearest {
+                            second = nearest;
+                            nearest = distance;
+                        } else if distance < second {
+                            second = distance;
+                        }
+                    }
+                }
+            }
+            // Every cell in the next shell is at least the radius away along some axis
+            if (radius * radius) as f64 >= second {
+                break;
+            }
+            radius += 1;
+        }
+        (nearest.sqrt(), second.sqrt())
+    }
+
+    pub fn feature(&self, point : &Point3, feature : CellFeature) -> f64 {
+        let (nearest, second) = self.distances(point);
+        match feature {
+            CellFeature::F1 => nearest,
+            CellFeature::F2 => second,
+            CellFeature::F2MinusF1 => second - nearest
+        }
+    }
+
+    fn offset(&self, corner : &Point3) -> Vec3 {
+        let i = (corner.x() as i64 as usize) & (POINT_COUNT - 1);
+        let j = (corner.y() as i64 as usize) & (POINT_COUNT - 1);
+        let k = (corner.z() as i64 as usize) & (POINT_COUNT - 1);
+        let index = self.x_permute[i] ^ self.y_permute[j] ^ self.z_permute[k];
+        self.offsets[index as usize]
+    }
+}